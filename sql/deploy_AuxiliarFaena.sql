@@ -13,22 +13,92 @@ GO
 SET QUOTED_IDENTIFIER ON
 GO
 
+-- Lineas de produccion, cada una con el tipo de mercaderia que etiqueta --
+CREATE TABLE [cambiarEtiquetas].[Lineas] (
+    [linea]           VARCHAR (16) NOT NULL,
+    [mercaderia_tipo] INT          NOT NULL, --> MercaderiaTipo_Id de las mercaderias de la linea
+    CONSTRAINT [PK_Lineas] PRIMARY KEY CLUSTERED ([linea] ASC)
+);
+GO
+
+-- Solo faena, 1 son las medias reses. Las otras lineas se agregan con el MercaderiaTipo_Id --
+-- que tienen en TwinsDBQuatro053, hasta entonces la interfaz de esa linea no lista mercaderias --
+INSERT INTO [cambiarEtiquetas].[Lineas] ([linea], [mercaderia_tipo])
+VALUES ('faena', 1)
+GO
+
 CREATE TABLE [cambiarEtiquetas].[FaenaEtiquetas] (
     [id]       TINYINT       IDENTITY (1, 1) NOT NULL,
     [enable]   BIT           NOT NULL,
     [etiqueta] VARCHAR (200) NOT NULL,
     [label]    VARCHAR (16)  NOT NULL,
     [color]    VARCHAR (7)   NOT NULL,
-    [dpi300]   BIT           CONSTRAINT [DEFAULT_FaenaEtiquetas_300dpi] DEFAULT ((1)) NOT NULL,
-    CONSTRAINT [PK_FaenaEtiquetas] PRIMARY KEY CLUSTERED ([id] ASC)
+    [dpi]      SMALLINT      CONSTRAINT [DEFAULT_FaenaEtiquetas_dpi] DEFAULT ((300)) NOT NULL,
+    [hotkey]   VARCHAR (16)  NULL, --> Tecla del boton (nombre de egui::Key), nula usa su posicion
+    [linea]    VARCHAR (16)  CONSTRAINT [DEFAULT_FaenaEtiquetas_linea] DEFAULT ('faena') NOT NULL, --> Linea de produccion que muestra el boton
+    CONSTRAINT [PK_FaenaEtiquetas] PRIMARY KEY CLUSTERED ([id] ASC),
+    CONSTRAINT [CK_FaenaEtiquetas_dpi] CHECK ([dpi] IN (203, 300, 600)),
+    CONSTRAINT [FK_FaenaEtiquetas_Lineas] FOREIGN KEY ([linea]) REFERENCES [cambiarEtiquetas].[Lineas] ([linea])
 );
 
 GO
 CREATE UNIQUE NONCLUSTERED INDEX [IX_Unique_FaenaEtiquetas]
     ON [cambiarEtiquetas].[FaenaEtiquetas]([etiqueta] ASC);
 
+GO
+-- Una tecla por boton en cada linea y resolucion --
+CREATE UNIQUE NONCLUSTERED INDEX [IX_Unique_FaenaEtiquetas_hotkey]
+    ON [cambiarEtiquetas].[FaenaEtiquetas]([linea] ASC, [dpi] ASC, [hotkey] ASC)
+    WHERE [hotkey] IS NOT NULL;
+
+
+-- Rol de Sistemas, habilita la pantalla de administracion de etiquetas --
+CREATE ROLE [EtiquetasAdmin];
+GO
+GRANT SELECT, INSERT, UPDATE, DELETE ON [cambiarEtiquetas].[FaenaEtiquetas] TO [EtiquetasAdmin];
+GO
+
+
+CREATE TABLE [cambiarEtiquetas].[CambiosEtiqueta] (
+    [id]       INT           IDENTITY (1, 1) NOT NULL,
+    [fecha]    DATETIME2 (0) CONSTRAINT [DEFAULT_CambiosEtiqueta_fecha] DEFAULT (SYSDATETIME()) NOT NULL,
+    [estacion] VARCHAR (64)  NOT NULL,
+    [usuario]  VARCHAR (128) NOT NULL,
+    [anterior] VARCHAR (MAX) NOT NULL, --> Etiquetas previas separadas por comas, puede haber mas de una
+    [nueva]    VARCHAR (200) NOT NULL,
+    [cantidad] INT           NOT NULL, --> Cantidad de mercaderias a cambiar
+    [codigo]   INT           NOT NULL, --> 0 si fue exitoso, sino el codigo de error de la SP
+    CONSTRAINT [PK_CambiosEtiqueta] PRIMARY KEY CLUSTERED ([id] ASC)
+);
+
+GO
+CREATE NONCLUSTERED INDEX [IX_CambiosEtiqueta_fecha]
+    ON [cambiarEtiquetas].[CambiosEtiqueta]([fecha] DESC);
+
 
-INSERT INTO [cambiarEtiquetas].[FaenaEtiquetas]
+-- Migraciones aplicadas (ver sql/migrations), este script ya incluye todas --
+CREATE TABLE [cambiarEtiquetas].[SchemaVersion] (
+    [version] INT           NOT NULL,
+    [nombre]  VARCHAR (64)  NOT NULL,
+    [fecha]   DATETIME2 (0) CONSTRAINT [DEFAULT_SchemaVersion_fecha] DEFAULT (SYSDATETIME()) NOT NULL,
+    [usuario] VARCHAR (128) CONSTRAINT [DEFAULT_SchemaVersion_usuario] DEFAULT (SUSER_SNAME()) NOT NULL,
+    CONSTRAINT [PK_SchemaVersion] PRIMARY KEY CLUSTERED ([version] ASC)
+);
+GO
+
+INSERT INTO [cambiarEtiquetas].[SchemaVersion] ([version], [nombre])
+VALUES
+    (1, 'cambios_etiqueta'),
+    (2, 'etiquetas_admin'),
+    (3, 'cambiar_etiquetas'),
+    (4, 'hotkey'),
+    (5, 'resolucion'),
+    (6, 'linea'),
+    (7, 'lineas')
+GO
+
+
+INSERT INTO [cambiarEtiquetas].[FaenaEtiquetas] ([enable], [etiqueta], [label], [color])
 VALUES 
     (1, 'faena_h_aa_2copias', 'H AA 2', '#664980'),
     (1, 'faena_h_aa_3copias', 'H AA 3', '#4C709A'),
@@ -66,12 +136,13 @@ GO
 
 
 CREATE PROCEDURE [cambiarEtiquetas].[ListarMedias]
-	@mercaderias varchar(MAX) output
+	@mercaderias varchar(MAX) output,
+	@linea varchar(16) = 'faena' -- Linea de produccion, lista su tipo de mercaderia
 AS
 BEGIN
 	SELECT STRING_AGG(Id, ',')
 	FROM [TwinsDBQuatro053].[configuracion].[Mercaderias]
-	WHERE MercaderiaTipo_Id = 1 -- 1 es la id de Media
+	WHERE MercaderiaTipo_Id = (SELECT [mercaderia_tipo] FROM [cambiarEtiquetas].[Lineas] WHERE [linea] = @linea)
 END
 GO
 
@@ -89,12 +160,19 @@ GO
  *      @etiqueta --> Nombre de la etiqueta a usar en las mercaderias [Falla si es null o vacio].
  *      @prueba --> 0 habilita el modo prueba, 1 habilita el modo producción
  *
+ *  Resultado (modo producción):
+ *      1. [actualizadas] --> Cantidad de mercaderias actualizadas.
+ *      2. [Mercaderia_Id] --> Mercaderias de la lista que mantuvieron su etiqueta (inexistentes,
+ *                             inactivas o sin actualizar), vacio si se actualizaron todas.
+ *
  *  Error Code: 56450
  *       Causa: No se ha podido actualizar la mercaderia.
  *  Error Code: 58450
  *       Causa: No existe el producto.
  *   Warn Code: 57450
  *       Causa: Se intento actualizar la mercaderia con una etiqueta no habilitada.
+ *  Error Code: 59450
+ *       Causa: La etiqueta es de otra linea que la mercaderia.
  */
 CREATE PROCEDURE [cambiarEtiquetas].[CambiarEtiquetas]
 	@mercaderias varchar(250) = '',
@@ -106,10 +184,14 @@ BEGIN
 
     DECLARE @enable BIT --> Estado de la etiqueta
     DECLARE @faltantes INT --> Cantidad de productos que no se pudieron actualizar
+    DECLARE @actualizadas INT --> Cantidad de productos actualizados
     DECLARE @list TABLE(item INT) --> Lista de productos por ID
     DECLARE @err_msg NVARCHAR(MAX) --> Mensaje de error 56450
     DECLARE @warn_msg NVARCHAR(200) --> Mensaje de advertencia 57450
     DECLARE @mercaderia_activa TABLE(id INT) --> Lista de mercaderia activa para actualizar
+    DECLARE @faltantes_list TABLE(id INT) --> Lista de mercaderia que mantuvo su etiqueta
+    DECLARE @linea VARCHAR(16) --> Linea de produccion de la etiqueta
+    DECLARE @tipo INT --> Tipo de mercaderia de la linea
     
     -- Paramos el proceso si alguna de los parametros esta vacio --
 	IF (@mercaderias is null or @mercaderias = '')
@@ -143,10 +225,28 @@ BEGIN
         THROW 58450, @err_msg, 4;
     END
 
+    -- Revisamos que la etiqueta sea de la linea de las mercaderias --
+    SELECT @linea = Etiqueta.[linea], @tipo = Linea.[mercaderia_tipo]
+    FROM [cambiarEtiquetas].[FaenaEtiquetas] AS Etiqueta
+        INNER JOIN [cambiarEtiquetas].[Lineas] AS Linea ON Etiqueta.[linea] = Linea.[linea]
+    WHERE Etiqueta.[etiqueta] = @etiqueta
+
+    IF EXISTS (
+        SELECT 1 FROM [TwinsDBQuatro053].[configuracion].[Mercaderias]
+        WHERE Id IN (SELECT id FROM @mercaderia_activa) AND MercaderiaTipo_Id <> @tipo
+    )
+    BEGIN
+        SET @err_msg = 'Etiqueta ' + @etiqueta + ' de la linea ' + @linea + ', no corresponde a la mercaderia.';
+        THROW 59450, @err_msg, 5;
+    END
+
     -- Update query para actualizar las etiquetas, filtra por mercaderia activa --
     IF @prueba = 1
+    BEGIN
 	    UPDATE [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] SET sEtiqueta = @etiqueta
         WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa)
+        SET @actualizadas = @@ROWCOUNT
+    END
     ELSE
     BEGIN
         PRINT 'Cambiar Etiquetas (modo prueba)'
@@ -158,17 +258,29 @@ BEGIN
         RETURN -- No hace falta continuar con el proceso en modo prueba --
     END
 
-    -- Selecciona las mercaderias que no se han podido actualizar --
-    SELECT @faltantes = COUNT(*), @err_msg = STRING_AGG(Mercaderia_Id, ',')
+    -- Mercaderias que no se han podido actualizar: las inexistentes o inactivas de la lista --
+    -- y las activas que quedaron sin la etiqueta nueva --
+    INSERT INTO @faltantes_list
+    SELECT item FROM @list WHERE item NOT IN (SELECT id FROM @mercaderia_activa)
+    UNION
+    SELECT Mercaderia_Id
     FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja]
-    WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa) AND sEtiqueta <> @etiqueta
+    WHERE Mercaderia_Id in (SELECT id FROM @mercaderia_activa)
+        AND (sEtiqueta IS NULL OR sEtiqueta <> @etiqueta)
+
+    SELECT @faltantes = COUNT(*), @err_msg = STRING_AGG(id, ',') FROM @faltantes_list
 
     -- Genera una falla si no se modifico ninguna mercaderia --
-    IF @faltantes = (SELECT COUNT(*) FROM @list)
+    IF @faltantes = (SELECT COUNT(DISTINCT item) FROM @list)
     BEGIN
         SET @err_msg = 'No se han podido actualizar esta mercaderia: ' + @err_msg;
         THROW 56450, @err_msg, 4;
     END
+
+    -- Informa el resultado, incluyendo las mercaderias que no se actualizaron --
+    SELECT @actualizadas AS actualizadas
+
+    SELECT id AS Mercaderia_Id FROM @faltantes_list
 END
 GO
 
@@ -0,0 +1,17 @@
+/*
+ *  Migracion: Version del esquema
+ *  Descripcion: Crea la tabla SchemaVersion, donde `faena_cli migrate` registra cada migracion
+ *               aplicada. La crea el mismo programa antes de migrar, este script queda como
+ *               referencia para hacerlo a mano.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+IF OBJECT_ID('[cambiarEtiquetas].[SchemaVersion]', 'U') IS NULL
+CREATE TABLE [cambiarEtiquetas].[SchemaVersion] (
+    [version] INT           NOT NULL,
+    [nombre]  VARCHAR (64)  NOT NULL,
+    [fecha]   DATETIME2 (0) CONSTRAINT [DEFAULT_SchemaVersion_fecha] DEFAULT (SYSDATETIME()) NOT NULL,
+    [usuario] VARCHAR (128) CONSTRAINT [DEFAULT_SchemaVersion_usuario] DEFAULT (SUSER_SNAME()) NOT NULL,
+    CONSTRAINT [PK_SchemaVersion] PRIMARY KEY CLUSTERED ([version] ASC)
+);
+GO
@@ -0,0 +1,23 @@
+/*
+ *  Migracion: Auditoria de cambios
+ *  Descripcion: Crea la tabla CambiosEtiqueta, donde se registra cada cambio de etiqueta con la
+ *               estacion y el usuario que lo hizo, las etiquetas previas y el resultado de la SP.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+CREATE TABLE [cambiarEtiquetas].[CambiosEtiqueta] (
+    [id]       INT           IDENTITY (1, 1) NOT NULL,
+    [fecha]    DATETIME2 (0) CONSTRAINT [DEFAULT_CambiosEtiqueta_fecha] DEFAULT (SYSDATETIME()) NOT NULL,
+    [estacion] VARCHAR (64)  NOT NULL,
+    [usuario]  VARCHAR (128) NOT NULL,
+    [anterior] VARCHAR (MAX) NOT NULL, --> Etiquetas previas separadas por comas, puede haber mas de una
+    [nueva]    VARCHAR (200) NOT NULL,
+    [cantidad] INT           NOT NULL, --> Cantidad de mercaderias a cambiar
+    [codigo]   INT           NOT NULL, --> 0 si fue exitoso, sino el codigo de error de la SP
+    CONSTRAINT [PK_CambiosEtiqueta] PRIMARY KEY CLUSTERED ([id] ASC)
+);
+GO
+
+CREATE NONCLUSTERED INDEX [IX_CambiosEtiqueta_fecha]
+    ON [cambiarEtiquetas].[CambiosEtiqueta]([fecha] DESC);
+GO
@@ -0,0 +1,12 @@
+/*
+ *  Migracion: Rol de administracion
+ *  Descripcion: Crea el rol EtiquetasAdmin, que habilita la pantalla de administracion de
+ *               etiquetas, con permisos sobre FaenaEtiquetas.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+CREATE ROLE [EtiquetasAdmin];
+GO
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON [cambiarEtiquetas].[FaenaEtiquetas] TO [EtiquetasAdmin];
+GO
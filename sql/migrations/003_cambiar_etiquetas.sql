@@ -0,0 +1,100 @@
+/*
+ *  Migracion: Resultado de CambiarEtiquetas
+ *  Descripcion: CambiarEtiquetas devuelve la cantidad de mercaderias actualizadas y, en un segundo
+ *               resultado, las de la lista que mantuvieron su etiqueta: las inexistentes o
+ *               inactivas y las activas que quedaron con otra etiqueta o sin ella.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+ALTER PROCEDURE [cambiarEtiquetas].[CambiarEtiquetas]
+	@mercaderias varchar(250) = '',
+	@etiqueta varchar(60) = '',
+    @prueba BIT = 0
+AS
+BEGIN
+	SET NOCOUNT OFF;
+
+    DECLARE @enable BIT --> Estado de la etiqueta
+    DECLARE @faltantes INT --> Cantidad de productos que no se pudieron actualizar
+    DECLARE @actualizadas INT --> Cantidad de productos actualizados
+    DECLARE @list TABLE(item INT) --> Lista de productos por ID
+    DECLARE @err_msg NVARCHAR(MAX) --> Mensaje de error 56450
+    DECLARE @warn_msg NVARCHAR(200) --> Mensaje de advertencia 57450
+    DECLARE @mercaderia_activa TABLE(id INT) --> Lista de mercaderia activa para actualizar
+    DECLARE @faltantes_list TABLE(id INT) --> Lista de mercaderia que mantuvo su etiqueta
+    
+    -- Paramos el proceso si alguna de los parametros esta vacio --
+	IF (@mercaderias is null or @mercaderias = '')
+        RAISERROR('La variable @mercaderia esta vacia', 11, 1)
+
+	IF (@etiqueta is null or @etiqueta = '')
+		RAISERROR('La variable @etiqueta esta vacia', 11, 2)
+
+    -- Parseamos la lista de ids a una tabla --
+    INSERT INTO @list SELECT * FROM [cambiarEtiquetas].[SplitToList](@mercaderias)
+    
+    -- Revisamos si la etiqueta esta habilitada para produccion --
+    SET @enable = (SELECT [enable] FROM [cambiarEtiquetas].[FaenaEtiquetas] WHERE [etiqueta] = @etiqueta)
+    IF (@enable = 0)
+    BEGIN
+        SET @warn_msg = 'Etiqueta ' + @etiqueta + ' no habilitada para producción.';
+        THROW 57450, @warn_msg, 3;
+    END
+
+    -- Filtra la lista de mercaderia para solo las activas --
+    INSERT INTO @mercaderia_activa SELECT Mercaderia.Id
+    FROM [TwinsDBQuatro053].[configuracion].[Mercaderias] as Mercaderia 
+        INNER JOIN [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] as EtiquetasCaja
+        ON Mercaderia.Id = EtiquetasCaja.Mercaderia_Id
+    WHERE Mercaderia.Id in (SELECT * FROM @list) AND Mercaderia.bActivo = 1
+
+    -- Genera una falla si no existe ninguna mercaderia --
+    IF NOT EXISTS (SELECT 1 FROM @mercaderia_activa)
+        BEGIN
+        SET @err_msg = 'Mercaderia (' + @mercaderias + ') no existe';
+        THROW 58450, @err_msg, 4;
+    END
+
+    -- Update query para actualizar las etiquetas, filtra por mercaderia activa --
+    IF @prueba = 1
+    BEGIN
+	    UPDATE [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] SET sEtiqueta = @etiqueta
+        WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa)
+        SET @actualizadas = @@ROWCOUNT
+    END
+    ELSE
+    BEGIN
+        PRINT 'Cambiar Etiquetas (modo prueba)'
+
+        SELECT *
+        FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja]
+        WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa)
+
+        RETURN -- No hace falta continuar con el proceso en modo prueba --
+    END
+
+    -- Mercaderias que no se han podido actualizar: las inexistentes o inactivas de la lista --
+    -- y las activas que quedaron sin la etiqueta nueva --
+    INSERT INTO @faltantes_list
+    SELECT item FROM @list WHERE item NOT IN (SELECT id FROM @mercaderia_activa)
+    UNION
+    SELECT Mercaderia_Id
+    FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja]
+    WHERE Mercaderia_Id in (SELECT id FROM @mercaderia_activa)
+        AND (sEtiqueta IS NULL OR sEtiqueta <> @etiqueta)
+
+    SELECT @faltantes = COUNT(*), @err_msg = STRING_AGG(id, ',') FROM @faltantes_list
+
+    -- Genera una falla si no se modifico ninguna mercaderia --
+    IF @faltantes = (SELECT COUNT(DISTINCT item) FROM @list)
+    BEGIN
+        SET @err_msg = 'No se han podido actualizar esta mercaderia: ' + @err_msg;
+        THROW 56450, @err_msg, 4;
+    END
+
+    -- Informa el resultado, incluyendo las mercaderias que no se actualizaron --
+    SELECT @actualizadas AS actualizadas
+
+    SELECT id AS Mercaderia_Id FROM @faltantes_list
+END
+GO
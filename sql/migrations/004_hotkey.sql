@@ -0,0 +1,11 @@
+/*
+ *  Migracion: Tecla de cada boton
+ *  Descripcion: Agrega la columna [hotkey] a FaenaEtiquetas, con el nombre de la tecla de
+ *               `egui::Key` que activa el boton; nula usa su posicion en la grilla. El indice
+ *               que impide repetirla por linea y resolucion se crea en 006_linea.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD [hotkey] VARCHAR (16) NULL;
+GO
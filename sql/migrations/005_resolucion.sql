@@ -0,0 +1,20 @@
+/*
+ *  Migracion: Resolucion de las etiquetas
+ *  Descripcion: Reemplaza la columna [dpi300] de FaenaEtiquetas por [dpi], con la resolucion en
+ *               puntos por pulgada, para soportar impresoras de 600 dpi.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD [dpi] SMALLINT CONSTRAINT [DEFAULT_FaenaEtiquetas_dpi] DEFAULT ((300)) NOT NULL;
+GO
+
+UPDATE [cambiarEtiquetas].[FaenaEtiquetas]
+SET [dpi] = CASE WHEN [dpi300] = 1 THEN 300 ELSE 203 END;
+GO
+
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas] DROP CONSTRAINT [DEFAULT_FaenaEtiquetas_300dpi];
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas] DROP COLUMN [dpi300];
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD CONSTRAINT [CK_FaenaEtiquetas_dpi] CHECK ([dpi] IN (203, 300, 600));
+GO
@@ -0,0 +1,32 @@
+/*
+ *  Migracion: Linea de produccion
+ *  Descripcion: Agrega la columna [linea] a FaenaEtiquetas, para que cada linea (faena, cuarteo,
+ *               despostada) tenga sus propias etiquetas, y el parametro @tipo a ListarMedias.
+ *               Las etiquetas existentes quedan en la linea de faena. La tecla de cada boton
+ *               pasa a ser unica por linea y resolucion.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD [linea] VARCHAR (16) CONSTRAINT [DEFAULT_FaenaEtiquetas_linea] DEFAULT ('faena') NOT NULL;
+GO
+
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD CONSTRAINT [CK_FaenaEtiquetas_linea] CHECK ([linea] IN ('faena', 'cuarteo', 'despostada'));
+GO
+
+CREATE UNIQUE NONCLUSTERED INDEX [IX_Unique_FaenaEtiquetas_hotkey]
+    ON [cambiarEtiquetas].[FaenaEtiquetas]([linea] ASC, [dpi] ASC, [hotkey] ASC)
+    WHERE [hotkey] IS NOT NULL;
+GO
+
+ALTER PROCEDURE [cambiarEtiquetas].[ListarMedias]
+	@mercaderias varchar(MAX) output,
+	@tipo int = 1 -- MercaderiaTipo_Id de la linea, 1 es la id de Media
+AS
+BEGIN
+	SELECT STRING_AGG(Id, ',')
+	FROM [TwinsDBQuatro053].[configuracion].[Mercaderias]
+	WHERE MercaderiaTipo_Id = @tipo
+END
+GO
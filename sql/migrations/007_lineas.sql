@@ -0,0 +1,147 @@
+/*
+ *  Migracion: Tabla de lineas
+ *  Descripcion: Crea la tabla Lineas con el tipo de mercaderia de cada linea de produccion, que
+ *               reemplaza al CHECK de FaenaEtiquetas.[linea] por una clave foranea. ListarMedias
+ *               pasa a recibir la linea y CambiarEtiquetas rechaza (59450) las etiquetas de otra
+ *               linea que la mercaderia.
+ *
+ *  Proyecto: Faena Etiquetas
+ */
+CREATE TABLE [cambiarEtiquetas].[Lineas] (
+    [linea]           VARCHAR (16) NOT NULL,
+    [mercaderia_tipo] INT          NOT NULL, --> MercaderiaTipo_Id de las mercaderias de la linea
+    CONSTRAINT [PK_Lineas] PRIMARY KEY CLUSTERED ([linea] ASC)
+);
+GO
+
+-- Solo faena, 1 son las medias reses. Las otras lineas se agregan con el MercaderiaTipo_Id --
+-- que tienen en TwinsDBQuatro053, hasta entonces la interfaz de esa linea no lista mercaderias --
+INSERT INTO [cambiarEtiquetas].[Lineas] ([linea], [mercaderia_tipo])
+VALUES ('faena', 1)
+GO
+
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas] DROP CONSTRAINT [CK_FaenaEtiquetas_linea];
+ALTER TABLE [cambiarEtiquetas].[FaenaEtiquetas]
+    ADD CONSTRAINT [FK_FaenaEtiquetas_Lineas] FOREIGN KEY ([linea]) REFERENCES [cambiarEtiquetas].[Lineas] ([linea]);
+GO
+
+ALTER PROCEDURE [cambiarEtiquetas].[ListarMedias]
+	@mercaderias varchar(MAX) output,
+	@linea varchar(16) = 'faena' -- Linea de produccion, lista su tipo de mercaderia
+AS
+BEGIN
+	SELECT STRING_AGG(Id, ',')
+	FROM [TwinsDBQuatro053].[configuracion].[Mercaderias]
+	WHERE MercaderiaTipo_Id = (SELECT [mercaderia_tipo] FROM [cambiarEtiquetas].[Lineas] WHERE [linea] = @linea)
+END
+GO
+
+ALTER PROCEDURE [cambiarEtiquetas].[CambiarEtiquetas]
+	@mercaderias varchar(250) = '',
+	@etiqueta varchar(60) = '',
+    @prueba BIT = 0
+AS
+BEGIN
+	SET NOCOUNT OFF;
+
+    DECLARE @enable BIT --> Estado de la etiqueta
+    DECLARE @faltantes INT --> Cantidad de productos que no se pudieron actualizar
+    DECLARE @actualizadas INT --> Cantidad de productos actualizados
+    DECLARE @list TABLE(item INT) --> Lista de productos por ID
+    DECLARE @err_msg NVARCHAR(MAX) --> Mensaje de error 56450
+    DECLARE @warn_msg NVARCHAR(200) --> Mensaje de advertencia 57450
+    DECLARE @mercaderia_activa TABLE(id INT) --> Lista de mercaderia activa para actualizar
+    DECLARE @faltantes_list TABLE(id INT) --> Lista de mercaderia que mantuvo su etiqueta
+    DECLARE @linea VARCHAR(16) --> Linea de produccion de la etiqueta
+    DECLARE @tipo INT --> Tipo de mercaderia de la linea
+    
+    -- Paramos el proceso si alguna de los parametros esta vacio --
+	IF (@mercaderias is null or @mercaderias = '')
+        RAISERROR('La variable @mercaderia esta vacia', 11, 1)
+
+	IF (@etiqueta is null or @etiqueta = '')
+		RAISERROR('La variable @etiqueta esta vacia', 11, 2)
+
+    -- Parseamos la lista de ids a una tabla --
+    INSERT INTO @list SELECT * FROM [cambiarEtiquetas].[SplitToList](@mercaderias)
+    
+    -- Revisamos si la etiqueta esta habilitada para produccion --
+    SET @enable = (SELECT [enable] FROM [cambiarEtiquetas].[FaenaEtiquetas] WHERE [etiqueta] = @etiqueta)
+    IF (@enable = 0)
+    BEGIN
+        SET @warn_msg = 'Etiqueta ' + @etiqueta + ' no habilitada para producción.';
+        THROW 57450, @warn_msg, 3;
+    END
+
+    -- Filtra la lista de mercaderia para solo las activas --
+    INSERT INTO @mercaderia_activa SELECT Mercaderia.Id
+    FROM [TwinsDBQuatro053].[configuracion].[Mercaderias] as Mercaderia 
+        INNER JOIN [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] as EtiquetasCaja
+        ON Mercaderia.Id = EtiquetasCaja.Mercaderia_Id
+    WHERE Mercaderia.Id in (SELECT * FROM @list) AND Mercaderia.bActivo = 1
+
+    -- Genera una falla si no existe ninguna mercaderia --
+    IF NOT EXISTS (SELECT 1 FROM @mercaderia_activa)
+        BEGIN
+        SET @err_msg = 'Mercaderia (' + @mercaderias + ') no existe';
+        THROW 58450, @err_msg, 4;
+    END
+
+    -- Revisamos que la etiqueta sea de la linea de las mercaderias --
+    SELECT @linea = Etiqueta.[linea], @tipo = Linea.[mercaderia_tipo]
+    FROM [cambiarEtiquetas].[FaenaEtiquetas] AS Etiqueta
+        INNER JOIN [cambiarEtiquetas].[Lineas] AS Linea ON Etiqueta.[linea] = Linea.[linea]
+    WHERE Etiqueta.[etiqueta] = @etiqueta
+
+    IF EXISTS (
+        SELECT 1 FROM [TwinsDBQuatro053].[configuracion].[Mercaderias]
+        WHERE Id IN (SELECT id FROM @mercaderia_activa) AND MercaderiaTipo_Id <> @tipo
+    )
+    BEGIN
+        SET @err_msg = 'Etiqueta ' + @etiqueta + ' de la linea ' + @linea + ', no corresponde a la mercaderia.';
+        THROW 59450, @err_msg, 5;
+    END
+
+    -- Update query para actualizar las etiquetas, filtra por mercaderia activa --
+    IF @prueba = 1
+    BEGIN
+	    UPDATE [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] SET sEtiqueta = @etiqueta
+        WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa)
+        SET @actualizadas = @@ROWCOUNT
+    END
+    ELSE
+    BEGIN
+        PRINT 'Cambiar Etiquetas (modo prueba)'
+
+        SELECT *
+        FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja]
+        WHERE Mercaderia_Id in (SELECT * FROM @mercaderia_activa)
+
+        RETURN -- No hace falta continuar con el proceso en modo prueba --
+    END
+
+    -- Mercaderias que no se han podido actualizar: las inexistentes o inactivas de la lista --
+    -- y las activas que quedaron sin la etiqueta nueva --
+    INSERT INTO @faltantes_list
+    SELECT item FROM @list WHERE item NOT IN (SELECT id FROM @mercaderia_activa)
+    UNION
+    SELECT Mercaderia_Id
+    FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja]
+    WHERE Mercaderia_Id in (SELECT id FROM @mercaderia_activa)
+        AND (sEtiqueta IS NULL OR sEtiqueta <> @etiqueta)
+
+    SELECT @faltantes = COUNT(*), @err_msg = STRING_AGG(id, ',') FROM @faltantes_list
+
+    -- Genera una falla si no se modifico ninguna mercaderia --
+    IF @faltantes = (SELECT COUNT(DISTINCT item) FROM @list)
+    BEGIN
+        SET @err_msg = 'No se han podido actualizar esta mercaderia: ' + @err_msg;
+        THROW 56450, @err_msg, 4;
+    END
+
+    -- Informa el resultado, incluyendo las mercaderias que no se actualizaron --
+    SELECT @actualizadas AS actualizadas
+
+    SELECT id AS Mercaderia_Id FROM @faltantes_list
+END
+GO
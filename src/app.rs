@@ -1,28 +1,46 @@
+mod admin;
+mod contraste;
+mod diagnostico;
+mod grid;
+mod history;
+mod programacion;
+mod selection;
 mod toggle_switch;
 
 use crate::{
-    config::Config,
+    backend::{self, ConnectionState},
+    config::{self, Config},
     constants,
-    sql::{Etiquetas, SQL},
+    i18n::{self, Idioma},
+    sql::{
+        Cambio, CambioError, EtiquetaCaja, Etiquetas, FilaRechazada, RegistroCambio, Resolucion,
+    },
+    worker::{Command, Response, Worker},
 };
-use async_std::task::block_on;
-use egui::{Color32, Ui, Vec2};
+use egui::{Color32, Stroke, Ui, Vec2};
 use egui_modal::{Icon, Modal, ModalStyle};
-use std::{
-    sync::{Arc, Mutex},
-    thread::JoinHandle,
-};
-use tiberius::{ExecuteResult, Result};
+use grid::GridLayout;
+use std::{collections::BTreeMap, time::Duration};
 use toggle_switch::toggle;
-use tracing::error;
+use tracing::{error, warn};
+use web_time::Instant;
 
 /// Posibles estados de la aplicación
 #[derive(Debug)]
 enum AppStatus {
     Loading,
     Error,
-    Warn,
+    /// Fallo o rechazo del ultimo pedido
+    Fallo(CambioError),
     Ok,
+    /// El cambio se aplico, pero estas mercaderias mantuvieron su etiqueta
+    Parcial(Vec<EtiquetaCaja>),
+    /// El cambio se aplico, pero no se pudo confirmar cuantas mercaderias cambiaron
+    SinConfirmar,
+    /// Resultado del modo prueba, esperando confirmacion
+    Preview,
+    /// Se deshizo el ultimo cambio
+    Restored,
 }
 
 #[derive(Debug)]
@@ -31,88 +49,194 @@ pub struct App {
     enables_count: u8,
     /// Lista de mercaderia separada por comas, ejemplo: "11,12,13,14"
     faena_ids: String,
+    /// Etiqueta aplicada actualmente a cada mercaderia de `faena_ids`
+    etiquetas: Vec<EtiquetaCaja>,
     /// Estado de conexion de la aplicación
     status: AppStatus,
     /// Tabla intermedia con informacion sobre cada etiqueta disponible
     table: Option<Vec<Etiquetas>>,
-    /// Conexion con el servidor de SQL
-    sql_client: Option<Arc<Mutex<SQL>>>,
-    /// Hilo secundario para ejecutar las llamadas al servidor
-    handler: Option<JoinHandle<Result<ExecuteResult>>>,
+    /// Filas de la tabla intermedia descartadas por invalidas
+    rechazadas: Vec<FilaRechazada>,
+    /// Muestra la ventana con el detalle de las filas rechazadas
+    show_diagnostico: bool,
+    /// Tarea que ejecuta las llamadas al servidor de SQL (o su version en memoria)
+    worker: Worker,
+    /// Estado de la conexion, se muestra en la barra de estado
+    connection: ConnectionState,
+    /// Momento de la ultima recarga de la tabla, para la recarga periodica
+    last_refresh: Instant,
+    /// Momento en que cambiaron los botones, la grilla destella durante un instante
+    flash: Option<Instant>,
+    /// Ventana de administracion de la tabla intermedia, `None` si esta cerrada
+    admin: Option<admin::AdminView>,
+    /// Proximo cambio de etiqueta programado en la configuracion
+    programado: Option<programacion::Pendiente>,
+    /// Etiquetas previas al ultimo cambio, vacio si no hay nada para deshacer
+    undo: Vec<EtiquetaCaja>,
+    /// Modo prueba, muestra las mercaderias afectadas antes de confirmar el cambio
+    dry_run: bool,
+    /// Etiqueta a aplicar una vez confirmado el modo prueba
+    pending: Option<String>,
+    /// Mercaderias que modificaria la etiqueta pendiente
+    preview: Vec<Cambio>,
+    /// Muestra el panel de historial
+    show_history: bool,
+    /// Ultimos cambios de etiqueta registrados
+    historial: Vec<RegistroCambio>,
+    /// Muestra el panel de seleccion de medias
+    show_selection: bool,
     /// Configuraciones del programa
     config: Config,
 }
 
 impl App {
-    pub async fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let config: Config = confy::load("faena_etiquetas", "config")
-            .expect("No se pudo generar el archivo de configuracion.");
-        let sql_client = SQL::new_connection().await;
-
-        if let Ok(mut sql) = sql_client {
-            let (table, enables_count) = App::update_table(&mut sql, config.is_dpi300).await;
-            let faena_ids = sql.query_ids().await;
-
-            Self {
-                table,
-                enables_count,
-                handler: None,
-                status: AppStatus::Error,
-                sql_client: Some(Arc::new(Mutex::new(sql))),
-                faena_ids: if let Err(err) = faena_ids {
-                    error!("On sql::query_ids: {err}");
-                    String::new()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let config: Config =
+            config::load().expect("No se pudo generar el archivo de configuracion.");
+        i18n::set_idioma(config.idioma);
+
+        // El worker conecta en segundo plano, al lograrlo avisa y se cargan los datos.
+        let ctx = cc.egui_ctx.clone();
+        let worker = Worker::spawn(config.clone(), move || ctx.request_repaint());
+
+        Self {
+            enables_count: 0,
+            faena_ids: String::new(),
+            etiquetas: Vec::new(),
+            status: AppStatus::Error,
+            table: None,
+            rechazadas: Vec::new(),
+            show_diagnostico: false,
+            worker,
+            connection: ConnectionState::Reconnecting { attempt: 0 },
+            last_refresh: Instant::now(),
+            flash: None,
+            admin: None,
+            programado: backend::ahora_local()
+                .and_then(|ahora| programacion::proximo(&config.programacion, ahora)),
+            undo: Vec::new(),
+            dry_run: false,
+            pending: None,
+            preview: Vec::new(),
+            show_history: false,
+            historial: Vec::new(),
+            show_selection: false,
+            config,
+        }
+    }
+
+    /// Indica si hay conexion con la base de datos, sin ella los botones quedan deshabilitados.
+    fn is_connected(&self) -> bool {
+        self.connection == ConnectionState::Connected
+    }
+
+    /// Procesa las respuestas del worker.
+    fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::Connection(state) => {
+                self.connection = state;
+                if state == ConnectionState::Connected {
+                    self.refresh_table();
+                }
+            }
+            Response::Refresh(Ok(snapshot)) => {
+                // Destella solo si cambiaron los botones, no en la carga inicial.
+                if self
+                    .table
+                    .as_ref()
+                    .is_some_and(|table| botones(table).ne(botones(&snapshot.table)))
+                {
+                    self.flash = Some(Instant::now());
+                }
+
+                self.enables_count = snapshot.table.iter().filter(|e| e.enable).count() as u8;
+                self.table = Some(snapshot.table);
+                self.actualizar_rechazadas(snapshot.rechazadas);
+                self.faena_ids = snapshot.faena_ids;
+                self.etiquetas = snapshot.etiquetas;
+                if let Some(historial) = snapshot.historial {
+                    self.historial = historial;
+                }
+            }
+            Response::Refresh(Err(err)) => {
+                error!("On sql::query_table: {err}");
+                (self.table, self.enables_count) = (None, 0);
+            }
+            Response::Historial(historial) => {
+                self.historial = historial.unwrap_or_else(|err| {
+                    error!("On sql::query_historial: {err}");
+                    Vec::new()
+                });
+            }
+            Response::Cambio(Ok(cambio)) => {
+                let sin_cambiar = cambio.sin_cambiar();
+                self.undo = cambio.anteriores;
+                // Actualizamos la tabla intermedia
+                self.refresh_table();
+                self.status = if cambio.sin_confirmar {
+                    AppStatus::SinConfirmar
+                } else if sin_cambiar.is_empty() {
+                    AppStatus::Ok
                 } else {
-                    faena_ids.unwrap()
-                },
-                config,
+                    warn!(faltantes = ?cambio.faltantes, "Cambio de etiqueta parcial");
+                    AppStatus::Parcial(sin_cambiar)
+                }
             }
-        } else {
-            let err = sql_client.as_ref().err().unwrap();
-            error!("On sql::new_connection: {err}");
-
-            Self {
-                enables_count: 0,
-                faena_ids: String::new(),
-                status: AppStatus::Error,
-                table: None,
-                sql_client: None,
-                handler: None,
-                config,
+            Response::Preview(Ok(preview)) => {
+                self.preview = preview;
+                self.status = AppStatus::Preview
+            }
+            Response::Cambio(Err(err)) => self.handle_error("cambiar", err),
+            Response::Preview(Err(err)) => self.handle_error("probar", err),
+            Response::Restored(Ok(_)) => {
+                self.refresh_table();
+                self.status = AppStatus::Restored
             }
+            Response::Restored(Err(err)) => self.handle_error("deshacer", err),
+            Response::Admin(result) => self.admin_response(result),
         }
     }
 
-    async fn update_table(sql_client: &mut SQL, is_300dpi: bool) -> (Option<Vec<Etiquetas>>, u8) {
-        let table = sql_client.query_table(is_300dpi).await;
-        if let Err(err) = table.as_ref() {
-            error!("On sql::query_table: {err}");
-            return (None, 0);
+    /// Cantidad de mercaderias seleccionadas por etiqueta aplicada, de mayor a menor.
+    fn etiquetas_aplicadas(&self) -> Vec<(&str, usize)> {
+        let mut count = BTreeMap::new();
+        for eti in self
+            .etiquetas
+            .iter()
+            .filter(|eti| self.is_selected(eti.mercaderia_id))
+        {
+            *count.entry(eti.nombre()).or_insert(0) += 1;
         }
 
-        let table = table.unwrap();
-        let value = table.iter().filter(|e| e.enable).count() as u8;
+        let mut aplicadas: Vec<(&str, usize)> = count.into_iter().collect();
+        aplicadas.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        aplicadas
+    }
 
-        (Some(table), value)
+    /// Nombre a mostrar de una etiqueta, si no esta en la tabla intermedia se muestra tal cual.
+    fn label_of<'a>(&'a self, etiqueta: &'a str) -> &'a str {
+        self.table
+            .iter()
+            .flatten()
+            .find(|eti| eti.etiqueta == etiqueta)
+            .map_or(etiqueta, |eti| eti.label.as_str())
     }
 
     #[inline]
     /// Arma la grilla con todos los botones a mostrar
-    fn build_grid(&mut self, ui: &mut Ui, modal: &Modal) {
+    fn build_grid(&mut self, ui: &mut Ui, modal: &Modal, layout: &GridLayout) {
         // Fuente mas grande para los botones.
         ui.style_mut().text_styles.insert(
             egui::TextStyle::Button,
-            egui::FontId::new(
-                constants::BUTTON_FONT_SIZE,
-                eframe::epaint::FontFamily::Proportional,
-            ),
+            egui::FontId::new(layout.font_size(), eframe::epaint::FontFamily::Proportional),
         );
 
-        egui::Grid::new("faena_grid")
-            .spacing(egui::Vec2::new(
-                constants::GRID_SPACE,
-                constants::GRID_SPACE,
-            ))
+        let aplicadas = self.etiquetas_aplicadas();
+        let mut clicked = None;
+        // Las teclas no cambian la etiqueta con el aviso abierto o escribiendo en otra ventana.
+        let teclado = self.is_connected() && !modal.is_open() && !ui.ctx().wants_keyboard_input();
+        let grid = egui::Grid::new("faena_grid")
+            .spacing(Vec2::splat(layout.spacing))
             .show(ui, |ui| {
                 if self.table.is_none() {
                     return;
@@ -120,6 +244,8 @@ impl App {
 
                 // Itera sobre los botones habilitados
                 let table = self.table.as_ref().unwrap().iter().filter(|eti| eti.enable);
+                let configuradas: Vec<egui::Key> =
+                    table.clone().filter_map(Etiquetas::hotkey_key).collect();
                 for (i, eti) in table.enumerate() {
                     // Debug! to remove. Para mostrar menos botones de los habilitados.
                     #[cfg(debug_assertions)]
@@ -127,98 +253,486 @@ impl App {
                         break;
                     }
 
+                    let hotkey = hotkey(i, eti, &configuradas);
+                    let fill = Color32::from_hex(&eti.color).unwrap_or(Color32::GRAY);
+                    // Texto blanco o negro segun el fondo, el tema no conoce el color del boton.
+                    let texto = contraste::texto(fill);
+                    let mut boton =
+                        egui::Button::new(egui::RichText::new(&eti.label).strong().color(texto));
+                    if let Some(key) = hotkey {
+                        boton = boton
+                            .shortcut_text(egui::RichText::new(key.symbol_or_name()).color(texto));
+                    }
+
+                    let presionada =
+                        teclado && hotkey.is_some_and(|key| ui.input(|i| i.key_pressed(key)));
                     if ui
                         .add_enabled(
                             // Deshabilita el boton en caso de fallar la conexion con SQL Server.
-                            self.sql_client.is_some(),
-                            egui::Button::new(egui::RichText::new(&eti.label).strong())
-                                .fill(Color32::from_hex(&eti.color).unwrap())
-                                .min_size(Vec2::new(
-                                    constants::BUTTON_WIDTH,
-                                    constants::BUTTON_HEIGHT,
-                                )),
+                            self.is_connected(),
+                            boton
+                                .fill(fill)
+                                // Resalta la etiqueta aplicada actualmente.
+                                .stroke(
+                                    if aplicadas
+                                        .iter()
+                                        .any(|(aplicada, _)| *aplicada == eti.etiqueta)
+                                    {
+                                        Stroke::new(constants::APPLIED_STROKE, Color32::WHITE)
+                                    } else {
+                                        Stroke::NONE
+                                    },
+                                )
+                                .min_size(layout.button),
                         )
                         .clicked()
+                        || presionada
                     {
-                        // Ejecuta la SP para cambiar la etiqueta.
-                        if let Some(sql) = &mut self.sql_client {
-                            let sql = sql.clone();
-                            let eti = eti.etiqueta.clone();
-                            let ids = self.faena_ids.clone();
-
-                            // Pasamos la ejecucion de la query a otro hilo para no trabar la interfaz.
-                            self.status = AppStatus::Loading;
-                            self.handler = Some(std::thread::spawn(move || {
-                                block_on(sql.lock().unwrap().execute_cambiar_etiquetas(&ids, &eti))
-                            }));
-                        }
-
-                        modal.open()
+                        clicked = Some(eti.etiqueta.clone());
                     }
 
-                    // Cada 3 botones salta de fila
-                    if (i + 1) % 3 == 0 {
+                    // Completada la fila, salta a la siguiente
+                    if (i + 1) % layout.columns == 0 {
                         ui.end_row()
                     }
                 }
             });
+
+        self.paint_flash(ui, grid.response.rect);
+
+        if let Some(etiqueta) = clicked {
+            // En modo prueba la SP solo informa que cambiaria, el cambio real se confirma en el modal.
+            if self.dry_run {
+                self.probar_cambio(etiqueta);
+            } else {
+                self.cambiar_etiquetas(etiqueta);
+            }
+
+            modal.open()
+        }
     }
 
-    fn refresh_table(&mut self) {
-        if let Some(sql) = &mut self.sql_client {
-            (self.table, self.enables_count) = block_on(App::update_table(
-                sql.clone().lock().as_mut().unwrap(),
-                self.config.is_dpi300,
+    /// Ejecuta la SP para cambiar la etiqueta.
+    fn cambiar_etiquetas(&mut self, etiqueta: String) {
+        self.status = AppStatus::Loading;
+        self.worker.send(Command::CambiarEtiquetas {
+            ids: self.target_ids(),
+            etiqueta,
+        });
+    }
+
+    /// Devuelve las mercaderias a la etiqueta que tenian antes del ultimo cambio.
+    fn deshacer(&mut self) {
+        self.status = AppStatus::Loading;
+        self.worker
+            .send(Command::Restore(std::mem::take(&mut self.undo)));
+    }
+
+    /// Ejecuta la SP en modo prueba, el resultado queda en `preview` para confirmarlo.
+    fn probar_cambio(&mut self, etiqueta: String) {
+        self.status = AppStatus::Loading;
+        self.worker.send(Command::ProbarCambio {
+            ids: self.target_ids(),
+            etiqueta: etiqueta.clone(),
+        });
+        self.pending = Some(etiqueta);
+    }
+
+    /// Actualiza el estado segun el error devuelto por la SP, `operacion` queda en el log.
+    fn handle_error(&mut self, operacion: &str, err: anyhow::Error) {
+        let error = CambioError::from_error(&err);
+        error.log(operacion, &err);
+
+        match error {
+            // Actualizamos la tabla intermedia
+            CambioError::NoHabilitada => self.refresh_table(),
+            // El worker se encarga de reconectar.
+            CambioError::SinConexion | CambioError::Timeout => {}
+            _ => self.refresh_history(),
+        }
+        self.status = AppStatus::Fallo(error);
+    }
+
+    /// Tabla con las mercaderias que modificaria el cambio pendiente.
+    fn show_preview(&self, ui: &mut Ui) {
+        ui.style_mut().text_styles.insert(
+            egui::TextStyle::Body,
+            egui::FontId::new(
+                constants::PREVIEW_FONT_SIZE,
+                eframe::epaint::FontFamily::Proportional,
+            ),
+        );
+
+        let cambian = self
+            .preview
+            .iter()
+            .filter(|cambio| cambio.actual != cambio.nueva)
+            .count();
+        ui.label(i18n::tf(
+            "preview.cambiarian",
+            &[("cambian", &cambian), ("total", &self.preview.len())],
+        ));
+
+        egui::ScrollArea::vertical()
+            .max_height(constants::PREVIEW_HEIGHT)
+            .show(ui, |ui| {
+                egui::Grid::new("preview_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong(i18n::t("columna.mercaderia"));
+                        ui.strong(i18n::t("columna.actual"));
+                        ui.label("");
+                        ui.strong(i18n::t("columna.nueva"));
+                        ui.end_row();
+
+                        for cambio in &self.preview {
+                            // Las mercaderias que ya tienen la etiqueta no cambian.
+                            let color = if cambio.actual == cambio.nueva {
+                                ui.visuals().weak_text_color()
+                            } else {
+                                ui.visuals().strong_text_color()
+                            };
+
+                            ui.colored_label(color, cambio.mercaderia_id.to_string());
+                            ui.colored_label(color, &cambio.actual);
+                            ui.colored_label(color, "→");
+                            ui.colored_label(color, &cambio.nueva);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Lista de las mercaderias que no se actualizaron, con la etiqueta que conservan.
+    fn show_parcial(&self, ui: &mut Ui, sin_cambiar: &[EtiquetaCaja]) {
+        ui.label(
+            egui::RichText::new(i18n::tf(
+                "parcial.mantuvieron",
+                &[("cantidad", &sin_cambiar.len())],
             ))
+            .size(constants::HINT_FONT_SIZE),
+        );
+
+        egui::ScrollArea::vertical()
+            .max_height(constants::PREVIEW_HEIGHT)
+            .show(ui, |ui| {
+                egui::Grid::new("parcial_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong(i18n::t("columna.mercaderia"));
+                        ui.strong(i18n::t("columna.etiqueta"));
+                        ui.end_row();
+
+                        for eti in sin_cambiar {
+                            ui.label(eti.mercaderia_id.to_string());
+                            ui.label(self.label_of(eti.nombre()));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Indicador del estado de la conexion para la barra de estado.
+    fn connection_indicator(&self, ui: &mut Ui) {
+        let (color, text) = match self.connection {
+            ConnectionState::Connected => {
+                (Color32::GREEN, i18n::t("conexion.conectado").to_string())
+            }
+            ConnectionState::Reconnecting { attempt: 0 } => (
+                Color32::YELLOW,
+                i18n::t("conexion.reconectando").to_string(),
+            ),
+            ConnectionState::Reconnecting { attempt } => (
+                Color32::YELLOW,
+                i18n::tf("conexion.reintento", &[("intento", &(attempt + 1))]),
+            ),
+            ConnectionState::Offline { retry_at, .. } => {
+                let secs = retry_at.saturating_duration_since(Instant::now()).as_secs();
+                // Redibuja para mantener actualizada la cuenta regresiva.
+                ui.ctx().request_repaint_after(Duration::from_secs(1));
+                (
+                    Color32::RED,
+                    i18n::tf("conexion.offline", &[("segundos", &secs)]),
+                )
+            }
+        };
+
+        ui.colored_label(color, "●");
+        ui.label(text);
+    }
+
+    /// Botones contiguos con cada resolucion, devuelve si cambio la seleccionada.
+    fn resolucion_selector(&mut self, ui: &mut Ui) -> bool {
+        let anterior = self.config.resolucion;
+        ui.group(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for resolucion in Resolucion::ALL {
+                ui.selectable_value(
+                    &mut self.config.resolucion,
+                    resolucion,
+                    egui::RichText::new(resolucion.to_string()).heading(),
+                );
+            }
+        });
+
+        self.config.resolucion != anterior
+    }
+
+    /// Selector del idioma de la interfaz, el cambio se aplica al instante y queda guardado.
+    fn idioma_selector(&mut self, ui: &mut Ui) {
+        let anterior = self.config.idioma;
+        egui::ComboBox::from_id_source("idioma")
+            .selected_text(self.config.idioma.nombre())
+            .show_ui(ui, |ui| {
+                for idioma in Idioma::ALL {
+                    ui.selectable_value(&mut self.config.idioma, idioma, idioma.nombre());
+                }
+            })
+            .response
+            .on_hover_text(i18n::t("menu.idioma"));
+
+        if self.config.idioma != anterior {
+            i18n::set_idioma(self.config.idioma);
+            self.store_config();
+        }
+    }
+
+    fn store_config(&self) {
+        if let Err(error) = config::store(&self.config) {
+            error!("No se pudo guardar la configuracion debido a: {:#?}", error)
         }
     }
+
+    /// Recarga la tabla cada `refresh_interval` segundos, para reflejar los cambios que haga
+    /// Sistemas sin tener que apretar ⟳.
+    fn auto_refresh(&mut self, ctx: &egui::Context) {
+        if self.config.refresh_interval == 0 || !self.is_connected() {
+            return;
+        }
+
+        let interval = Duration::from_secs(self.config.refresh_interval);
+        let elapsed = self.last_refresh.elapsed();
+        if elapsed >= interval {
+            self.refresh_table();
+            ctx.request_repaint_after(interval);
+        } else {
+            ctx.request_repaint_after(interval - elapsed);
+        }
+    }
+
+    /// Remarca la grilla con un borde que se desvanece luego de un cambio en los botones.
+    fn paint_flash(&mut self, ui: &Ui, rect: egui::Rect) {
+        let Some(start) = self.flash else {
+            return;
+        };
+
+        let t = start.elapsed().as_secs_f32() / constants::FLASH_DURATION;
+        if t >= 1.0 {
+            self.flash = None;
+            return;
+        }
+
+        let spacing = self.config.grid.spacing;
+        ui.painter().rect_stroke(
+            rect.expand(spacing / 2.0),
+            spacing / 4.0,
+            Stroke::new(
+                constants::FLASH_STROKE,
+                Color32::WHITE.gamma_multiply(1.0 - t),
+            ),
+        );
+        ui.ctx().request_repaint();
+    }
+
+    /// Pide al worker recargar la tabla intermedia, las medias y sus etiquetas.
+    fn refresh_table(&mut self) {
+        self.last_refresh = Instant::now();
+        self.worker.send(Command::Refresh {
+            resolucion: self.config.resolucion,
+            history_len: self.show_history.then_some(self.config.history_len),
+        });
+    }
+}
+
+/// Tecla del boton: la de la tabla intermedia o, si no tiene, el numero de su posicion. El
+/// numero se omite si otro boton lo tiene configurado, la tabla tiene prioridad.
+fn hotkey(posicion: usize, eti: &Etiquetas, configuradas: &[egui::Key]) -> Option<egui::Key> {
+    match &eti.hotkey {
+        Some(_) => eti.hotkey_key(),
+        None => constants::POSITION_KEYS
+            .get(posicion)
+            .copied()
+            .filter(|key| !configuradas.contains(key)),
+    }
+}
+
+/// Que puede hacer el operador ante el error, con el codigo de la SP para Sistemas.
+fn show_sugerencia(ui: &mut Ui, error: CambioError) {
+    ui.label(egui::RichText::new(error.sugerencia()).size(constants::HINT_FONT_SIZE));
+    if let Some(codigo) = error.codigo() {
+        ui.label(
+            egui::RichText::new(i18n::tf("modal.codigo", &[("codigo", &codigo)]))
+                .size(constants::HINT_FONT_SIZE)
+                .weak(),
+        );
+    }
+}
+
+/// Botones habilitados de la tabla, para detectar cambios entre recargas.
+fn botones(table: &[Etiquetas]) -> impl Iterator<Item = &Etiquetas> {
+    table.iter().filter(|eti| eti.enable)
 }
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Analizamos si el hilo secundario esta corriendo (is_some) y si ya termino con su tarea
-        if self.handler.as_ref().is_some_and(|task| task.is_finished()) {
-            let rc = self.handler.take().unwrap().join().unwrap();
-
-            if let Err(err) = rc {
-                if err.code().is_some_and(|code| code == constants::WARN_CODE) {
-                    // Actualizamos la tabla intermedia
-                    self.refresh_table();
-                    self.status = AppStatus::Warn
-                } else {
-                    self.status = AppStatus::Error;
-                    error!("Fallo el cambio de etiqueta. Motivo: {err}")
-                }
-            } else {
-                // Actualizamos la tabla intermedia
-                self.refresh_table();
-                self.status = AppStatus::Ok
-            }
+        while let Some(response) = self.worker.try_recv() {
+            self.handle_response(response);
         }
+        self.auto_refresh(ctx);
+        self.programacion(ctx);
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            egui::menu::bar(ui, |ui| ui.label("RUNFO S.A."));
+            egui::menu::bar(ui, |ui| {
+                ui.label("RUNFO S.A.");
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    self.idioma_selector(ui);
+
+                    if ui
+                        .add_enabled(
+                            self.is_connected(),
+                            egui::SelectableLabel::new(
+                                self.admin.is_some(),
+                                i18n::t("menu.administrar"),
+                            ),
+                        )
+                        .clicked()
+                    {
+                        self.toggle_admin();
+                    }
+
+                    if ui
+                        .selectable_label(self.show_history, i18n::t("menu.historial"))
+                        .clicked()
+                    {
+                        self.show_history = !self.show_history;
+                        self.refresh_history();
+                    }
+
+                    if ui
+                        .selectable_label(self.show_selection, i18n::t("menu.mercaderias"))
+                        .clicked()
+                    {
+                        self.show_selection = !self.show_selection;
+                    }
+                });
+            });
+        });
+
+        self.admin_window(ctx);
+        self.diagnostico_window(ctx);
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                self.connection_indicator(ui);
+                self.programacion_indicator(ui);
+                self.diagnostico_indicator(ui);
+            });
         });
 
+        if self.show_selection {
+            egui::SidePanel::left("selection_panel").show(ctx, |ui| self.selection_panel(ui));
+        }
+
+        if self.show_history {
+            egui::SidePanel::right("history_panel")
+                .default_width(constants::HISTORY_WIDTH)
+                .show(ctx, |ui| self.history_panel(ui));
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 // Titulo, avisa si esta desconectado.
-                if self.sql_client.is_none() {
+                if !self.is_connected() {
                     ui.heading(
-                        egui::RichText::new("desconectado")
+                        egui::RichText::new(i18n::t("titulo.desconectado"))
                             .color(Color32::RED)
                             .strong(),
                     );
                 } else {
-                    ui.heading("etiquetas faena");
+                    ui.heading(i18n::tf(
+                        "titulo",
+                        &[("linea", &self.config.estacion.linea)],
+                    ));
                 }
 
                 if ui.add(egui::Button::new("⟳")).clicked() {
                     // Actualizamos la tabla intermedia
                     self.refresh_table()
                 }
+
+                // Deshacer el ultimo cambio, muestra a que etiquetas se vuelve.
+                if !self.undo.is_empty() {
+                    let deshacer = ui
+                        .add_enabled(self.is_connected(), egui::Button::new(i18n::t("deshacer")))
+                        .on_hover_text(i18n::tf(
+                            "deshacer.vuelve_a",
+                            &[(
+                                "etiquetas",
+                                &backend::join_etiquetas(&self.undo)
+                                    .split(',')
+                                    .map(|eti| self.label_of(eti))
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                            )],
+                        ));
+
+                    if deshacer.clicked() {
+                        self.deshacer();
+                        Modal::new(ctx, "confirmation_modal").open();
+                    }
+                }
+
+                // Avisa cuando las etiquetas se aplican solo a una parte de las medias.
+                if !self.config.selection.is_empty() {
+                    let medias = self.medias();
+                    let seleccionadas = medias.iter().filter(|id| self.is_selected(**id)).count();
+                    ui.heading(
+                        egui::RichText::new(i18n::tf(
+                            "medias.seleccionadas",
+                            &[("seleccionadas", &seleccionadas), ("total", &medias.len())],
+                        ))
+                        .color(Color32::LIGHT_BLUE),
+                    );
+                }
+
+                if let [(etiqueta, _)] = self.etiquetas_aplicadas()[..] {
+                    ui.heading(i18n::tf(
+                        "aplicada",
+                        &[("etiqueta", &self.label_of(etiqueta))],
+                    ))
+                    .highlight();
+                }
             });
 
+            // Aviso de mercaderias con distintas etiquetas
+            let aplicadas = self.etiquetas_aplicadas();
+            if aplicadas.len() > 1 {
+                let detalle: Vec<String> = aplicadas
+                    .iter()
+                    .map(|(etiqueta, count)| format!("{} ({count})", self.label_of(etiqueta)))
+                    .collect();
+
+                ui.heading(
+                    egui::RichText::new(i18n::tf(
+                        "aplicadas.mezcladas",
+                        &[("detalle", &detalle.join(", "))],
+                    ))
+                    .color(Color32::YELLOW)
+                    .strong(),
+                );
+            }
+
             // Debug! to remove.
             #[cfg(debug_assertions)]
             ui.add(
@@ -229,108 +743,158 @@ impl eframe::App for App {
                 .text("(Debug!) Etiquetas"),
             );
 
-            // Dpi switch
+            // Selector de resolucion
             ui.horizontal(|ui| {
-                if ui.add(toggle(&mut self.config.is_dpi300)).changed() {
+                if self.resolucion_selector(ui) {
                     self.refresh_table();
-                    if let Err(error) = confy::store("faena_etiquetas", "config", &self.config) {
-                        error!("No se pudo guardar la configuracion debido a: {:#?}", error)
-                    }
+                    self.store_config();
                 }
-                ui.heading(if self.config.is_dpi300 {
-                    "300 dpi"
-                } else {
-                    "203 dpi"
-                })
-                .highlight();
+
+                ui.add_space(constants::GRID_SPACE);
+                ui.add(toggle(&mut self.dry_run));
+                ui.heading(i18n::t("modo_prueba"));
             });
 
-            // Espaciado vertical inteligente.
-            ui.add_space(
-                (ui.available_height()
-                    - constants::BUTTON_HEIGHT * f32::ceil(self.enables_count as f32 / 3.0)
-                    - constants::GRID_SPACE * f32::floor(self.enables_count as f32 / 3.0).min(1.0))
-                    / 2.0,
-            );
+            // La grilla ocupa el espacio restante, si los botones tienen tamaño fijo se centra.
+            let available = ui.available_size() - ui.spacing().item_spacing;
+            let layout = GridLayout::new(available, self.enables_count as usize, &self.config.grid);
+            let margen = ((available - layout.size()) / 2.0).max(Vec2::ZERO);
+            ui.add_space(margen.y);
 
             // Scrollbar en caso de que la lista de botones se vaya de la pantalla, almenos todavia podrian ser accesibles.
             egui::ScrollArea::both().show(ui, |ui| {
-                ui.with_layout(
-                    egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
-                    |ui| {
-                        // Espaciado horizontal inteligente.
-                        ui.add_space(
-                            (ui.available_width()
-                                - constants::BUTTON_WIDTH * self.enables_count.min(3) as f32
-                                - constants::GRID_SPACE
-                                    * f32::ceil(self.enables_count as f32 / 2.0).min(2.0))
-                                / 2.0,
+                ui.horizontal(|ui| {
+                    ui.add_space(margen.x);
+
+                    let modal = Modal::new(ctx, "confirmation_modal").with_style(&ModalStyle {
+                        default_height: Some(constants::MODAL_HEIGHT),
+                        default_width: Some(constants::MODAL_WIDTH),
+                        body_alignment: egui::Align::Center,
+                        icon_size: constants::ICON_SIZE,
+                        ..Default::default()
+                    });
+
+                    modal.show(|ui| {
+                        modal.title(
+                            ui,
+                            if self.pending.is_some() {
+                                i18n::t("modal.modo_prueba")
+                            } else {
+                                i18n::t("modal.cambiando")
+                            },
                         );
 
-                        let modal = Modal::new(ctx, "confirmation_modal").with_style(&ModalStyle {
-                            default_height: Some(constants::MODAL_HEIGHT),
-                            default_width: Some(constants::MODAL_WIDTH),
-                            body_alignment: egui::Align::Center,
-                            icon_size: constants::ICON_SIZE,
-                            ..Default::default()
-                        });
-
-                        modal.show(|ui| {
-                            modal.title(ui, "Cambiando etiquetas...");
-
-                            modal.frame(ui, |ui| {
-                                ui.style_mut().text_styles.insert(
-                                    egui::TextStyle::Body,
-                                    egui::FontId::new(
-                                        32.0,
-                                        eframe::epaint::FontFamily::Proportional,
-                                    ),
-                                );
+                        modal.frame(ui, |ui| {
+                            ui.style_mut().text_styles.insert(
+                                egui::TextStyle::Body,
+                                egui::FontId::new(32.0, eframe::epaint::FontFamily::Proportional),
+                            );
 
-                                match self.status {
-                                    AppStatus::Ok => modal.body_and_icon(
+                            match &self.status {
+                                AppStatus::Ok => {
+                                    modal.body_and_icon(ui, i18n::t("modal.ok"), Icon::Success)
+                                }
+                                AppStatus::Error => {
+                                    modal.body_and_icon(ui, i18n::t("error.otro"), Icon::Error)
+                                }
+                                AppStatus::Fallo(error) => {
+                                    modal.body_and_icon(
                                         ui,
-                                        "Etiquetas cambiadas exitosamente",
-                                        Icon::Success,
-                                    ),
-                                    AppStatus::Error => {
-                                        modal.body_and_icon(ui, constants::ERROR_MSG, Icon::Error)
-                                    }
-                                    AppStatus::Warn => {
-                                        modal.body_and_icon(ui, constants::WARN_MSG, Icon::Warning)
-                                    }
-                                    AppStatus::Loading => {
-                                        ui.add(egui::Spinner::new());
-                                    }
+                                        error.mensaje(),
+                                        if error.is_warning() {
+                                            Icon::Warning
+                                        } else {
+                                            Icon::Error
+                                        },
+                                    );
+                                    show_sugerencia(ui, *error);
                                 }
-                            });
-
-                            modal.buttons(ui, |ui| {
-                                if let AppStatus::Loading = self.status {
-                                    return;
+                                AppStatus::Loading => {
+                                    ui.add(egui::Spinner::new());
                                 }
+                                AppStatus::Parcial(sin_cambiar) => {
+                                    modal.body_and_icon(
+                                        ui,
+                                        i18n::t("modal.parcial"),
+                                        Icon::Warning,
+                                    );
+                                    self.show_parcial(ui, sin_cambiar);
+                                }
+                                AppStatus::SinConfirmar => modal.body_and_icon(
+                                    ui,
+                                    i18n::t("modal.sin_confirmar"),
+                                    Icon::Warning,
+                                ),
+                                AppStatus::Preview => self.show_preview(ui),
+                                AppStatus::Restored => modal.body_and_icon(
+                                    ui,
+                                    i18n::t("modal.restaurado"),
+                                    Icon::Success,
+                                ),
+                            }
+                        });
 
-                                ui.style_mut().text_styles.insert(
-                                    egui::TextStyle::Button,
-                                    egui::FontId::new(
-                                        24.0,
-                                        eframe::epaint::FontFamily::Proportional,
-                                    ),
-                                );
-
-                                // Tamaño del boton "Okay", calculado manualmente.
-                                // Hardcodeado porque es imposible de saber en esta parte.
-                                ui.add_space((ui.available_width() - 70.41656) / 2.0);
+                        modal.buttons(ui, |ui| {
+                            if let AppStatus::Loading = self.status {
+                                return;
+                            }
+
+                            ui.style_mut().text_styles.insert(
+                                egui::TextStyle::Button,
+                                egui::FontId::new(24.0, eframe::epaint::FontFamily::Proportional),
+                            );
+
+                            // Enter acepta y Escape cancela, para operar solo con el teclado.
+                            let (enter, escape) = ui.input(|i| {
+                                (
+                                    i.key_pressed(egui::Key::Enter),
+                                    i.key_pressed(egui::Key::Escape),
+                                )
+                            });
 
-                                if modal.button(ui, "Okay").clicked() {
+                            if let AppStatus::Preview = self.status {
+                                if modal
+                                    .caution_button(ui, i18n::t("modal.cancelar"))
+                                    .clicked()
+                                    || escape
+                                {
+                                    self.pending = None;
                                     self.status = AppStatus::Error;
+                                    modal.close();
                                 }
-                            });
+                                // No cierra el modal, pasa a mostrar el resultado del cambio.
+                                if ui.button(i18n::t("modal.confirmar")).clicked() || enter {
+                                    if let Some(etiqueta) = self.pending.take() {
+                                        self.cambiar_etiquetas(etiqueta);
+                                    }
+                                }
+                                return;
+                            }
+
+                            // Centra el boton, su ancho depende del texto en cada idioma.
+                            let aceptar = i18n::t("modal.aceptar");
+                            let ancho = ui.fonts(|fonts| {
+                                fonts
+                                    .layout_no_wrap(
+                                        aceptar.to_string(),
+                                        egui::TextStyle::Button.resolve(ui.style()),
+                                        Color32::PLACEHOLDER,
+                                    )
+                                    .size()
+                                    .x
+                            }) + 2.0 * ui.spacing().button_padding.x;
+                            ui.add_space((ui.available_width() - ancho) / 2.0);
+
+                            if modal.button(ui, aceptar).clicked() || enter || escape {
+                                self.pending = None;
+                                self.status = AppStatus::Error;
+                                modal.close();
+                            }
                         });
+                    });
 
-                        self.build_grid(ui, &modal);
-                    },
-                );
+                    self.build_grid(ui, &modal, &layout);
+                });
             });
         });
     }
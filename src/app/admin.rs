@@ -0,0 +1,248 @@
+//! Ventana para que Sistemas administre la tabla intermedia `FaenaEtiquetas` sin usar SSMS.
+//!
+//! El acceso lo controla el servidor: solo los usuarios del rol `admin_role` (o dueños de la
+//! base) obtienen la tabla y pueden modificarla.
+
+use super::{contraste, App};
+use crate::{
+    constants,
+    sql::{Etiquetas, Linea, Resolucion},
+    worker::{Admin, Command},
+};
+use egui::{color_picker::Alpha, Color32, TextEdit, Ui};
+use tracing::error;
+
+/// Estado de la ventana de administracion.
+#[derive(Debug)]
+pub(super) struct AdminView {
+    /// Tabla tal como esta en la base, `None` mientras carga o si no hay permisos
+    originales: Option<Vec<Etiquetas>>,
+    /// Copia editable de la tabla
+    filas: Vec<Etiquetas>,
+    /// Fila a agregar
+    nueva: Etiquetas,
+    /// Ultimo error informado por el servidor
+    error: Option<String>,
+    /// Etiqueta pendiente de confirmar su borrado
+    borrar: Option<u8>,
+    /// Hay un pedido en curso, los botones quedan deshabilitados hasta la respuesta
+    pendiente: bool,
+}
+
+impl App {
+    /// Abre o cierra la ventana de administracion.
+    pub(super) fn toggle_admin(&mut self) {
+        if self.admin.take().is_some() {
+            return;
+        }
+
+        self.admin = Some(AdminView {
+            originales: None,
+            filas: Vec::new(),
+            nueva: self.nueva_etiqueta(),
+            error: None,
+            borrar: None,
+            pendiente: true,
+        });
+        self.worker.send(Command::Admin(Admin::Abrir));
+    }
+
+    /// Procesa la respuesta a un pedido de administracion.
+    pub(super) fn admin_response(&mut self, result: anyhow::Result<Vec<Etiquetas>>) {
+        let Some(admin) = self.admin.as_mut() else {
+            return;
+        };
+        admin.pendiente = false;
+        admin.borrar = None;
+
+        match result {
+            Ok(table) => {
+                admin.filas = table.clone();
+                admin.originales = Some(table);
+                admin.error = None;
+                // La grilla refleja los cambios sin esperar a la recarga periodica.
+                self.refresh_table();
+            }
+            Err(err) => {
+                error!("On sql::admin: {err}");
+                admin.error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Fila vacia para agregar, con la linea del puesto y la resolucion seleccionada.
+    fn nueva_etiqueta(&self) -> Etiquetas {
+        Etiquetas {
+            id: 0,
+            enable: false,
+            etiqueta: String::new(),
+            label: String::new(),
+            color: "#808080".to_string(),
+            dpi: self.config.resolucion,
+            linea: self.config.estacion.linea,
+            hotkey: None,
+        }
+    }
+
+    /// Ventana con una fila editable por etiqueta y una fila extra para agregar.
+    pub(super) fn admin_window(&mut self, ctx: &egui::Context) {
+        let Some(mut admin) = self.admin.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut pedido = None;
+        egui::Window::new("administrar etiquetas")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if let Some(error) = &admin.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+
+                let Some(originales) = &admin.originales else {
+                    if admin.pendiente {
+                        ui.spinner();
+                    }
+                    return;
+                };
+
+                let habilitado = !admin.pendiente && self.is_connected();
+                egui::Grid::new("admin_grid")
+                    .striped(true)
+                    .num_columns(9)
+                    .show(ui, |ui| {
+                        for header in [
+                            "id",
+                            "habilitada",
+                            "etiqueta",
+                            "texto",
+                            "color",
+                            "resolucion",
+                            "linea",
+                            "tecla",
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for (fila, original) in admin.filas.iter_mut().zip(originales) {
+                            ui.label(fila.id.to_string());
+                            editar_fila(ui, fila);
+
+                            let valida = fila
+                                .validar()
+                                .and_then(|()| fila.validar_hotkey(originales));
+                            ui.horizontal(|ui| {
+                                let guardar = ui
+                                    .add_enabled(
+                                        habilitado && valida.is_ok() && fila != original,
+                                        egui::Button::new("guardar"),
+                                    )
+                                    .on_disabled_hover_text(
+                                        valida.err().map_or(String::new(), |err| err.to_string()),
+                                    );
+                                if guardar.clicked() {
+                                    pedido = Some(Admin::Editar(fila.clone()));
+                                }
+
+                                // El borrado se confirma con un segundo click.
+                                let confirmar = admin.borrar == Some(fila.id);
+                                let borrar = egui::Button::new(if confirmar {
+                                    egui::RichText::new("confirmar borrado").color(Color32::RED)
+                                } else {
+                                    egui::RichText::new("borrar")
+                                });
+                                if ui.add_enabled(habilitado, borrar).clicked() {
+                                    if confirmar {
+                                        pedido = Some(Admin::Borrar(fila.id));
+                                    } else {
+                                        admin.borrar = Some(fila.id);
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("nueva");
+                        editar_fila(ui, &mut admin.nueva);
+                        let valida = admin
+                            .nueva
+                            .validar()
+                            .and_then(|()| admin.nueva.validar_hotkey(originales));
+                        let agregar = ui
+                            .add_enabled(habilitado && valida.is_ok(), egui::Button::new("agregar"))
+                            .on_disabled_hover_text(
+                                valida.err().map_or(String::new(), |err| err.to_string()),
+                            );
+                        if agregar.clicked() {
+                            pedido = Some(Admin::Agregar(admin.nueva.clone()));
+                        }
+                        ui.end_row();
+                    });
+            });
+
+        if let Some(pedido) = pedido {
+            if let Admin::Agregar(_) = pedido {
+                admin.nueva = self.nueva_etiqueta();
+            }
+            admin.pendiente = true;
+            self.worker.send(Command::Admin(pedido));
+        }
+
+        if open {
+            self.admin = Some(admin);
+        }
+    }
+}
+
+/// Controles para editar cada columna de una fila, respetando el largo de cada una.
+fn editar_fila(ui: &mut Ui, fila: &mut Etiquetas) {
+    ui.checkbox(&mut fila.enable, "");
+    ui.add(TextEdit::singleline(&mut fila.etiqueta).char_limit(constants::ETIQUETA_LEN));
+    ui.add(
+        TextEdit::singleline(&mut fila.label)
+            .char_limit(constants::LABEL_LEN)
+            .desired_width(constants::ADMIN_LABEL_WIDTH),
+    );
+    ui.horizontal(|ui| {
+        let mut color = Color32::from_hex(&fila.color).unwrap_or(Color32::GRAY);
+        if egui::color_picker::color_edit_button_srgba(ui, &mut color, Alpha::Opaque).changed() {
+            fila.color = format!("#{:02X}{:02X}{:02X}", color.r(), color.g(), color.b());
+        }
+        ui.add(
+            TextEdit::singleline(&mut fila.color)
+                .char_limit(constants::COLOR_LEN)
+                .desired_width(constants::ADMIN_COLOR_WIDTH),
+        );
+        // Se puede guardar igual, es solo una advertencia.
+        if let Some(aviso) = contraste::aviso(&fila.color) {
+            ui.colored_label(Color32::YELLOW, "⚠").on_hover_text(aviso);
+        }
+    });
+    egui::ComboBox::from_id_source(("dpi", fila.id))
+        .selected_text(fila.dpi.to_string())
+        .show_ui(ui, |ui| {
+            for resolucion in Resolucion::ALL {
+                ui.selectable_value(&mut fila.dpi, resolucion, resolucion.to_string());
+            }
+        });
+    egui::ComboBox::from_id_source(("linea", fila.id))
+        .selected_text(fila.linea.to_string())
+        .show_ui(ui, |ui| {
+            for linea in Linea::ALL {
+                ui.selectable_value(&mut fila.linea, linea, linea.to_string());
+            }
+        });
+
+    // Vacia usa la posicion del boton en la grilla.
+    let mut hotkey = fila.hotkey.clone().unwrap_or_default();
+    let editar = TextEdit::singleline(&mut hotkey)
+        .char_limit(constants::HOTKEY_LEN)
+        .desired_width(constants::ADMIN_HOTKEY_WIDTH)
+        .hint_text("posicion");
+    if ui.add(editar).changed() {
+        fila.hotkey = Some(hotkey.trim().to_string()).filter(|hotkey| !hotkey.is_empty());
+    }
+}
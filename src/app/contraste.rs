@@ -0,0 +1,65 @@
+//! Color del texto de los botones segun su fondo, con la luminancia relativa y la relacion de
+//! contraste que define WCAG 2.
+
+use crate::{constants, i18n};
+use egui::Color32;
+
+/// Luminancia relativa del color, de 0 (negro) a 1 (blanco).
+pub(super) fn luminancia(color: Color32) -> f32 {
+    let canal = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * canal(color.r()) + 0.7152 * canal(color.g()) + 0.0722 * canal(color.b())
+}
+
+/// Relacion de contraste entre dos colores, de 1 a 21.
+pub(super) fn contraste(a: Color32, b: Color32) -> f32 {
+    let (a, b) = (luminancia(a), luminancia(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Texto blanco o negro, el que mas contrasta con el fondo.
+pub(super) fn texto(fondo: Color32) -> Color32 {
+    if contraste(fondo, Color32::BLACK) >= contraste(fondo, Color32::WHITE) {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    }
+}
+
+/// Aviso si el color de un boton no llega al contraste minimo ni con el mejor texto.
+pub(super) fn aviso(color: &str) -> Option<String> {
+    let fondo = Color32::from_hex(color).ok()?;
+    let relacion = contraste(fondo, texto(fondo));
+    (relacion < constants::MIN_CONTRAST).then(|| {
+        i18n::tf(
+            "contraste.aviso",
+            &[
+                ("color", &color),
+                ("relacion", &format!("{relacion:.1}")),
+                ("minimo", &constants::MIN_CONTRAST),
+            ],
+        )
+    })
+}
+
+#[test]
+fn test_contraste() {
+    assert!((contraste(Color32::WHITE, Color32::BLACK) - 21.0).abs() < 0.01);
+    assert!((contraste(Color32::RED, Color32::RED) - 1.0).abs() < 0.01);
+
+    // Colores de la tabla intermedia por defecto.
+    let hex = |color: &str| Color32::from_hex(color).unwrap();
+    assert_eq!(texto(hex("#B7AA5E")), Color32::BLACK);
+    assert_eq!(texto(hex("#664980")), Color32::WHITE);
+    assert_eq!(aviso("#B7AA5E"), None);
+
+    // Los grises medios no contrastan bien con ningun texto.
+    assert!(aviso("#808080").is_some());
+    assert_eq!(aviso("#GGG"), None);
+}
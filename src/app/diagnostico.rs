@@ -0,0 +1,105 @@
+//! Aviso de las filas de la tabla intermedia que no se muestran por tener datos invalidos, asi
+//! un error de carga en `FaenaEtiquetas` no deja al operador sin botones.
+
+use super::App;
+use crate::{i18n, sql::FilaRechazada};
+use egui::{Color32, Ui};
+use tracing::warn;
+
+impl App {
+    /// Guarda las filas rechazadas de la ultima recarga, registrando solo las nuevas para no
+    /// repetirlas en el log en cada recarga.
+    pub(super) fn actualizar_rechazadas(&mut self, rechazadas: Vec<FilaRechazada>) {
+        registrar_rechazadas(&self.rechazadas, &rechazadas);
+
+        if rechazadas.is_empty() {
+            self.show_diagnostico = false;
+        }
+        self.rechazadas = rechazadas;
+    }
+
+    /// Insignia en la barra de estado con la cantidad de filas rechazadas, abre el diagnostico.
+    pub(super) fn diagnostico_indicator(&mut self, ui: &mut Ui) {
+        if self.rechazadas.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        let texto = i18n::tf(
+            "diagnostico.invalidas",
+            &[("cantidad", &self.rechazadas.len())],
+        );
+        if ui
+            .selectable_label(
+                self.show_diagnostico,
+                egui::RichText::new(texto).color(Color32::YELLOW),
+            )
+            .on_hover_text(i18n::t("diagnostico.detalle"))
+            .clicked()
+        {
+            self.show_diagnostico = !self.show_diagnostico;
+        }
+    }
+
+    /// Lista de las filas rechazadas con el motivo de cada una.
+    pub(super) fn diagnostico_window(&mut self, ctx: &egui::Context) {
+        // Id fijo, el titulo cambia con el idioma.
+        egui::Window::new(i18n::t("diagnostico.titulo"))
+            .id(egui::Id::new("diagnostico"))
+            .open(&mut self.show_diagnostico)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(i18n::t("diagnostico.filas"));
+                egui::Grid::new("diagnostico_grid")
+                    .striped(true)
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for header in [
+                            "diagnostico.id",
+                            "diagnostico.etiqueta",
+                            "diagnostico.motivo",
+                        ] {
+                            ui.strong(i18n::t(header));
+                        }
+                        ui.end_row();
+
+                        for fila in &self.rechazadas {
+                            ui.label(fila.id.map_or("-".to_string(), |id| id.to_string()));
+                            ui.label(fila.etiqueta.as_deref().unwrap_or("-"));
+                            ui.colored_label(Color32::YELLOW, fila.motivo.mensaje());
+                            ui.end_row();
+                        }
+                    });
+                ui.label(i18n::t("diagnostico.corregir"));
+            });
+    }
+}
+
+/// Registra en el log las filas de `rechazadas` que no estaban en `anteriores`.
+fn registrar_rechazadas(anteriores: &[FilaRechazada], rechazadas: &[FilaRechazada]) {
+    for fila in rechazadas.iter().filter(|fila| !anteriores.contains(fila)) {
+        warn!(id = ?fila.id, etiqueta = ?fila.etiqueta, motivo = %fila.motivo, "Fila de la tabla intermedia rechazada");
+    }
+}
+
+#[test]
+fn test_registrar_rechazadas() {
+    let fila = FilaRechazada {
+        id: Some(3),
+        etiqueta: Some("faena_h_aa_4copias".to_string()),
+        motivo: crate::sql::MotivoRechazo::Color,
+    };
+
+    // Con el filtro por defecto la fila llega al log, y una sola vez.
+    let log = crate::capturar_log(|| registrar_rechazadas(&[], std::slice::from_ref(&fila)));
+    assert!(
+        log.contains("Fila de la tabla intermedia rechazada"),
+        "{log}"
+    );
+    assert!(log.contains("faena_h_aa_4copias"), "{log}");
+    let log = crate::capturar_log(|| {
+        registrar_rechazadas(std::slice::from_ref(&fila), std::slice::from_ref(&fila))
+    });
+    assert!(log.is_empty(), "{log}");
+}
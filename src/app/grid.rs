@@ -0,0 +1,118 @@
+//! Distribucion de la grilla de botones segun el espacio disponible, para que se vea completa
+//! en cualquier pantalla sin tener que desplazarse.
+
+use crate::{config, constants};
+use egui::Vec2;
+
+/// Columnas y tamaño de los botones calculados para un area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct GridLayout {
+    pub columns: usize,
+    pub rows: usize,
+    /// Tamaño de cada boton
+    pub button: Vec2,
+    pub spacing: f32,
+}
+
+impl GridLayout {
+    /// Calcula la distribucion de `count` botones en `available`, respetando lo que fije la
+    /// configuracion.
+    pub fn new(available: Vec2, count: usize, config: &config::Grid) -> Self {
+        let count = count.max(1);
+        let spacing = config.spacing.max(0.0);
+
+        // Tamaño de cada celda si los botones ocupan todo el area.
+        let celda = |columns: usize| {
+            let rows = count.div_ceil(columns);
+            Vec2::new(
+                (available.x - spacing * (columns - 1) as f32) / columns as f32,
+                (available.y - spacing * (rows - 1) as f32) / rows as f32,
+            )
+        };
+
+        // Sin columnas fijas, elige las que dejan los botones mas grandes en su proporcion.
+        let columns = if config.columns > 0 {
+            config.columns.min(count)
+        } else {
+            let mut mejor = 1;
+            for columns in 2..=count {
+                if escala(celda(columns)) > escala(celda(mejor)) {
+                    mejor = columns;
+                }
+            }
+            mejor
+        };
+
+        let celda = celda(columns);
+        let button = Vec2::new(
+            if config.button_width > 0.0 {
+                config.button_width
+            } else {
+                celda.x
+            },
+            if config.button_height > 0.0 {
+                config.button_height
+            } else {
+                celda.y
+            },
+        )
+        .max(Vec2::new(
+            constants::MIN_BUTTON_WIDTH,
+            constants::MIN_BUTTON_HEIGHT,
+        ));
+
+        Self {
+            columns,
+            rows: count.div_ceil(columns),
+            button,
+            spacing,
+        }
+    }
+
+    /// Tamaño total de la grilla.
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(
+            self.button.x * self.columns as f32 + self.spacing * (self.columns - 1) as f32,
+            self.button.y * self.rows as f32 + self.spacing * (self.rows - 1) as f32,
+        )
+    }
+
+    /// Tamaño de letra de los botones, proporcional a su tamaño.
+    pub fn font_size(&self) -> f32 {
+        (escala(self.button) / constants::BUTTON_ASPECT * constants::BUTTON_FONT_RATIO)
+            .min(constants::MAX_BUTTON_FONT_SIZE)
+    }
+}
+
+/// Ancho que tendria un boton con la proporcion preferida dentro de la celda.
+fn escala(celda: Vec2) -> f32 {
+    celda.x.min(celda.y * constants::BUTTON_ASPECT)
+}
+
+#[test]
+fn test_grid_layout() {
+    let config = config::Grid::default();
+    let spacing = config.spacing;
+
+    // Pantalla ancha: 6 botones en 3 columnas, la grilla ocupa todo el area.
+    let area = Vec2::new(1920.0, 800.0);
+    let layout = GridLayout::new(area, 6, &config);
+    assert_eq!((layout.columns, layout.rows), (3, 2));
+    assert!((layout.size() - area).length() < 0.01);
+
+    // Pantalla vertical: conviene una sola columna.
+    let layout = GridLayout::new(Vec2::new(600.0, 1600.0), 4, &config);
+    assert_eq!((layout.columns, layout.rows), (1, 4));
+    assert_eq!(layout.button.y, (1600.0 - 3.0 * spacing) / 4.0);
+
+    // Las columnas y el tamaño fijados en la configuracion se respetan.
+    let fija = config::Grid {
+        columns: 2,
+        button_height: 200.0,
+        ..config
+    };
+    let layout = GridLayout::new(area, 5, &fija);
+    assert_eq!((layout.columns, layout.rows), (2, 3));
+    assert_eq!(layout.button.y, 200.0);
+    assert!(layout.font_size() <= constants::MAX_BUTTON_FONT_SIZE);
+}
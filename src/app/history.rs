@@ -0,0 +1,80 @@
+//! Panel lateral con el historial de cambios de etiqueta.
+
+use super::App;
+use crate::{constants, i18n, sql::RegistroCambio, worker::Command};
+use egui::{Color32, Ui};
+
+impl App {
+    /// Actualiza el historial, solo si el panel esta abierto para no sumar consultas de mas.
+    pub(super) fn refresh_history(&mut self) {
+        if !self.show_history {
+            return;
+        }
+
+        self.worker
+            .send(Command::Historial(self.config.history_len));
+    }
+
+    /// Lista los ultimos cambios de etiqueta.
+    pub(super) fn history_panel(&self, ui: &mut Ui) {
+        ui.heading(i18n::t("menu.historial"));
+        ui.separator();
+
+        if self.historial.is_empty() {
+            ui.label(i18n::t("historial.vacio"));
+            return;
+        }
+
+        let format = time::macros::format_description!("[day]/[month] [hour]:[minute]");
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("history_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in [
+                        "historial.fecha",
+                        "historial.estacion",
+                        "historial.usuario",
+                        "historial.cambio",
+                        "historial.cantidad",
+                        "historial.resultado",
+                    ] {
+                        ui.strong(i18n::t(header));
+                    }
+                    ui.end_row();
+
+                    for registro in &self.historial {
+                        ui.label(registro.fecha.format(format).unwrap_or_default());
+                        ui.label(&registro.estacion);
+                        ui.label(&registro.usuario);
+                        ui.label(self.describe(registro));
+                        ui.label(registro.cantidad.to_string());
+                        if registro.codigo == 0 {
+                            ui.colored_label(Color32::GREEN, i18n::t("historial.ok"));
+                        } else if registro.codigo == constants::WARN_CODE as i32 {
+                            ui.colored_label(Color32::YELLOW, registro.codigo.to_string());
+                        } else {
+                            ui.colored_label(Color32::RED, registro.codigo.to_string());
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// "anterior → nueva" usando los nombres de la tabla intermedia.
+    fn describe(&self, registro: &RegistroCambio) -> String {
+        let anterior: Vec<&str> = registro
+            .anterior
+            .split(',')
+            .filter(|eti| !eti.is_empty())
+            .map(|eti| self.label_of(eti))
+            .collect();
+
+        format!(
+            "{} → {}",
+            anterior.join(", "),
+            self.label_of(&registro.nueva)
+        )
+    }
+}
@@ -0,0 +1,236 @@
+//! Cambios de etiqueta programados en la configuracion, se aplican solos a la hora indicada.
+//!
+//! Solo se aplican los cambios futuros: si el programa se abre despues de la hora de una
+//! regla, esa regla espera a su proxima ocurrencia. Si la interfaz esta ocupada a la hora del
+//! cambio (cargando o con un aviso de confirmacion abierto) se aplica al terminar, salvo que
+//! pase `PROGRAMACION_GRACIA`: entonces se saltea y queda en el log, igual que si el equipo
+//! estuvo suspendido.
+
+use super::{App, AppStatus};
+use crate::{backend, config::Programado, constants, i18n, sql::CambioError};
+use egui::{Color32, Ui};
+use egui_modal::Modal;
+use std::time::Duration;
+use time::{macros::format_description, PrimitiveDateTime, Time};
+use tracing::{error, info, warn};
+
+/// Proximo cambio programado.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Pendiente {
+    pub fecha: PrimitiveDateTime,
+    pub etiqueta: String,
+}
+
+impl App {
+    /// Aplica el cambio programado si llego su hora y busca el siguiente.
+    pub(super) fn programacion(&mut self, ctx: &egui::Context) {
+        let Some(pendiente) = &self.programado else {
+            return;
+        };
+
+        // Sin la hora local una regla de las 06:00 se aplicaria a las 06:00 UTC.
+        let Some(ahora) = backend::ahora_local() else {
+            self.programado = None;
+            return;
+        };
+        if pendiente.fecha > ahora {
+            // Despierta la interfaz a tiempo aunque nadie la use.
+            ctx.request_repaint_after(restante(ahora, pendiente.fecha));
+            return;
+        }
+        // No pisa un pedido en curso ni la confirmacion que espera el operador.
+        if !atrasado(pendiente.fecha, ahora)
+            && matches!(self.status, AppStatus::Loading | AppStatus::Preview)
+        {
+            ctx.request_repaint_after(Duration::from_secs(1));
+            return;
+        }
+
+        let Some(pendiente) = self.programado.take() else {
+            return;
+        };
+        // Desde ahora, asi las reglas vencidas no se disparan una tras otra.
+        self.programado = proximo(&self.config.programacion, ahora);
+
+        if atrasado(pendiente.fecha, ahora) {
+            registrar_salteado(&pendiente);
+            return;
+        }
+
+        // Se muestra el resultado como si el operador hubiera apretado el boton.
+        self.pending = None;
+        if self.is_connected() {
+            info!(etiqueta = %pendiente.etiqueta, fecha = %pendiente.fecha, "Cambio de etiqueta programado");
+            self.cambiar_etiquetas(pendiente.etiqueta);
+        } else {
+            error!(etiqueta = %pendiente.etiqueta, fecha = %pendiente.fecha, "Cambio de etiqueta programado sin conexion");
+            self.status = AppStatus::Fallo(CambioError::SinConexion);
+        }
+        Modal::new(ctx, "confirmation_modal").open();
+    }
+
+    /// Proximo cambio programado con su cuenta regresiva, para la barra de estado.
+    pub(super) fn programacion_indicator(&self, ui: &mut Ui) {
+        let Some(pendiente) = &self.programado else {
+            return;
+        };
+
+        let restante = restante(backend::ahora(), pendiente.fecha);
+        let secs = restante.as_secs();
+        let cuenta = if secs >= 3600 {
+            format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+        } else {
+            format!("{}m {:02}s", secs / 60, secs % 60)
+        };
+        // Con mas de una hora la cuenta solo muestra minutos.
+        ui.ctx()
+            .request_repaint_after(Duration::from_secs(if secs >= 3600 { 60 } else { 1 }));
+
+        ui.separator();
+        ui.colored_label(Color32::LIGHT_BLUE, "⏰");
+        let hora = format!(
+            "{:02}:{:02}",
+            pendiente.fecha.hour(),
+            pendiente.fecha.minute()
+        );
+        ui.label(i18n::tf(
+            "programacion.pendiente",
+            &[
+                ("etiqueta", &self.label_of(&pendiente.etiqueta)),
+                ("hora", &hora),
+                ("cuenta", &cuenta),
+            ],
+        ));
+    }
+}
+
+/// Deja en el log el cambio programado que no se aplico por atraso.
+fn registrar_salteado(pendiente: &Pendiente) {
+    warn!(etiqueta = %pendiente.etiqueta, fecha = %pendiente.fecha, "Cambio de etiqueta programado salteado por atraso");
+}
+
+/// Proximo cambio de todas las reglas posterior a `desde`.
+pub(super) fn proximo(reglas: &[Programado], desde: PrimitiveDateTime) -> Option<Pendiente> {
+    reglas
+        .iter()
+        .filter_map(|regla| {
+            Some(Pendiente {
+                fecha: proxima(regla, desde)?,
+                etiqueta: regla.etiqueta.clone(),
+            })
+        })
+        .min_by_key(|pendiente| pendiente.fecha)
+}
+
+/// Proxima ejecucion de la regla posterior a `desde`, `None` si la regla es invalida.
+fn proxima(regla: &Programado, desde: PrimitiveDateTime) -> Option<PrimitiveDateTime> {
+    let hora = match Time::parse(&regla.hora, format_description!("[hour]:[minute]")) {
+        Ok(hora) => hora,
+        Err(err) => {
+            error!(
+                "La hora '{}' de la programacion no es valida: {err}",
+                regla.hora
+            );
+            return None;
+        }
+    };
+
+    // La regla se repite como mucho una semana despues.
+    (0..=7)
+        .filter_map(|dias| desde.date().checked_add(time::Duration::days(dias)))
+        .filter(|fecha| regla.dias.is_empty() || regla.dias.contains(&fecha.weekday()))
+        .map(|fecha| fecha.with_time(hora))
+        .find(|fecha| *fecha > desde)
+}
+
+/// Si paso mas de `PROGRAMACION_GRACIA` desde la hora del cambio.
+fn atrasado(fecha: PrimitiveDateTime, ahora: PrimitiveDateTime) -> bool {
+    (ahora - fecha).whole_seconds() > constants::PROGRAMACION_GRACIA
+}
+
+/// Tiempo que falta hasta `fecha`.
+fn restante(ahora: PrimitiveDateTime, fecha: PrimitiveDateTime) -> Duration {
+    Duration::from_secs((fecha - ahora).whole_seconds().max(0) as u64)
+}
+
+#[test]
+fn test_proximo_programado() {
+    use time::{macros::datetime, Weekday};
+
+    let reglas = [
+        Programado {
+            hora: "06:00".to_string(),
+            dias: vec![
+                Weekday::Monday,
+                Weekday::Tuesday,
+                Weekday::Wednesday,
+                Weekday::Thursday,
+                Weekday::Friday,
+            ],
+            etiqueta: "faena_hilton_3copias".to_string(),
+        },
+        Programado {
+            hora: "14:30".to_string(),
+            dias: Vec::new(),
+            etiqueta: "faena_h_aa_2copias".to_string(),
+        },
+        Programado {
+            hora: "25:00".to_string(),
+            dias: Vec::new(),
+            etiqueta: "invalida".to_string(),
+        },
+    ];
+
+    // Viernes 2024-03-08 a las 10:00: la regla diaria de la tarde.
+    let pendiente = proximo(&reglas, datetime!(2024-03-08 10:00)).unwrap();
+    assert_eq!(pendiente.fecha, datetime!(2024-03-08 14:30));
+    assert_eq!(pendiente.etiqueta, "faena_h_aa_2copias");
+
+    // Viernes a la noche: el fin de semana solo queda la regla diaria.
+    let pendiente = proximo(&reglas, datetime!(2024-03-08 20:00)).unwrap();
+    assert_eq!(pendiente.fecha, datetime!(2024-03-09 14:30));
+
+    // Lunes temprano, y justo a la hora ya pasa a la siguiente.
+    let pendiente = proximo(&reglas, datetime!(2024-03-11 05:59)).unwrap();
+    assert_eq!(pendiente.fecha, datetime!(2024-03-11 06:00));
+    let pendiente = proximo(&reglas, datetime!(2024-03-11 06:00)).unwrap();
+    assert_eq!(pendiente.fecha, datetime!(2024-03-11 14:30));
+
+    assert_eq!(proximo(&reglas[2..], datetime!(2024-03-11 06:00)), None);
+}
+
+#[test]
+fn test_programado_atrasado() {
+    use time::macros::datetime;
+
+    let reglas = [Programado {
+        hora: "06:00".to_string(),
+        dias: Vec::new(),
+        etiqueta: "faena_hilton_3copias".to_string(),
+    }];
+
+    // Dentro del margen se aplica, despues se saltea.
+    assert!(!atrasado(
+        datetime!(2024-03-11 06:00),
+        datetime!(2024-03-11 06:04)
+    ));
+    assert!(atrasado(
+        datetime!(2024-03-11 06:00),
+        datetime!(2024-03-11 06:06)
+    ));
+
+    // Tras dos dias suspendido el siguiente es el de mañana, no los que se perdieron.
+    let pendiente = proximo(&reglas, datetime!(2024-03-13 09:00)).unwrap();
+    assert_eq!(pendiente.fecha, datetime!(2024-03-14 06:00));
+
+    // El salteado queda en el log con el filtro por defecto.
+    let log = crate::capturar_log(|| {
+        registrar_salteado(&Pendiente {
+            fecha: datetime!(2024-03-11 06:00),
+            etiqueta: "faena_hilton_3copias".to_string(),
+        })
+    });
+    assert!(log.contains("salteado por atraso"), "{log}");
+    assert!(log.contains("faena_hilton_3copias"), "{log}");
+    assert!(log.contains("fecha=2024-03-11"), "{log}");
+}
@@ -0,0 +1,97 @@
+//! Panel lateral para elegir a que medias se aplican las etiquetas.
+
+use super::App;
+use crate::i18n;
+use egui::Ui;
+
+impl App {
+    /// Ids de todas las medias devueltas por `ListarMedias`.
+    pub(super) fn medias(&self) -> Vec<i32> {
+        self.faena_ids
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .collect()
+    }
+
+    /// Indica si la media esta seleccionada, sin seleccion se aplica a todas.
+    pub(super) fn is_selected(&self, id: i32) -> bool {
+        self.config.selection.is_empty() || self.config.selection.contains(&id)
+    }
+
+    /// Lista de mercaderia separada por comas a la que se aplican las etiquetas.
+    pub(super) fn target_ids(&self) -> String {
+        if self.config.selection.is_empty() {
+            return self.faena_ids.clone();
+        }
+
+        self.medias()
+            .into_iter()
+            .filter(|id| self.config.selection.contains(id))
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Checkbox por cada media, la seleccion se guarda en la configuracion.
+    pub(super) fn selection_panel(&mut self, ui: &mut Ui) {
+        let medias = self.medias();
+
+        ui.heading(i18n::t("menu.mercaderias"));
+        ui.horizontal(|ui| {
+            if ui.button(i18n::t("seleccion.todas")).clicked() {
+                self.config.selection.clear();
+                self.store_config();
+            }
+            ui.label(i18n::tf(
+                "seleccion.cantidad",
+                &[
+                    (
+                        "seleccionadas",
+                        &medias.iter().filter(|id| self.is_selected(**id)).count(),
+                    ),
+                    ("total", &medias.len()),
+                ],
+            ));
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for id in &medias {
+                let mut checked = self.is_selected(*id);
+                let etiqueta = self
+                    .etiquetas
+                    .iter()
+                    .find(|eti| eti.mercaderia_id == *id)
+                    .map_or("", |eti| self.label_of(eti.nombre()));
+
+                if ui
+                    .checkbox(&mut checked, format!("{id}  {etiqueta}"))
+                    .changed()
+                {
+                    self.toggle_selection(*id, checked, &medias);
+                }
+            }
+        });
+    }
+
+    fn toggle_selection(&mut self, id: i32, checked: bool, medias: &[i32]) {
+        // Sin seleccion equivale a todas, se explicita antes de quitar una.
+        if self.config.selection.is_empty() {
+            self.config.selection = medias.to_vec();
+        }
+
+        if checked {
+            self.config.selection.push(id);
+        } else if self.config.selection.len() > 1 {
+            // Siempre tiene que quedar al menos una media seleccionada.
+            self.config.selection.retain(|sel| *sel != id);
+        }
+
+        // Si quedaron todas seleccionadas vuelve a "todas", asi se incluyen las medias nuevas.
+        if medias.iter().all(|id| self.config.selection.contains(id)) {
+            self.config.selection.clear();
+        }
+
+        self.store_config();
+    }
+}
@@ -0,0 +1,402 @@
+mod http;
+pub use http::{
+    CambioRequest, DeshacerRequest, ErrorResponse, Http, ESTACION_HEADER, USUARIO_HEADER,
+};
+
+mod memory;
+pub use memory::{Memory, Mercaderia};
+
+mod reconnect;
+pub use reconnect::{reconnect, ConnectionState};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sql::SQL;
+use crate::{
+    config::{BackendKind, Config},
+    sql::{Cambio, EtiquetaCaja, Etiquetas, Linea, RegistroCambio, Resolucion, TablaEtiquetas},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+/// Operaciones que la aplicación necesita de la base de datos.
+///
+/// Permite correr la interfaz contra el SQL Server o contra una version en memoria.
+#[async_trait]
+pub trait Backend: Send + std::fmt::Debug {
+    /// Obtiene la tabla intermedia de etiquetas de la linea y resolucion indicadas, separando
+    /// las filas invalidas.
+    async fn query_table(
+        &mut self,
+        linea: Linea,
+        resolucion: Resolucion,
+    ) -> anyhow::Result<TablaEtiquetas>;
+
+    /// Obtiene la lista de mercaderias de la linea, las de su `MercaderiaTipo_Id` en la tabla
+    /// `Lineas`, separada por comas.
+    async fn query_ids(&mut self, linea: Linea) -> anyhow::Result<String>;
+
+    /// Obtiene la etiqueta aplicada actualmente a cada mercaderia de la lista.
+    async fn query_etiquetas(&mut self, ids: &str) -> anyhow::Result<Vec<EtiquetaCaja>>;
+
+    /// Cambia la etiqueta de las mercaderias, devuelve la cantidad de filas afectadas, las
+    /// etiquetas que tenian antes del cambio y las mercaderias que no se pudieron actualizar.
+    /// Cada cambio, exitoso o no, queda registrado en el historial a nombre de `origen`.
+    ///
+    /// Los errores de la SP se devuelven como [`ProcedureError`].
+    async fn execute_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+        origen: &Origen,
+    ) -> anyhow::Result<CambioAplicado>;
+
+    /// Devuelve cada mercaderia a la etiqueta indicada en una sola transaccion, se usa para
+    /// deshacer un cambio. Queda registrado en el historial como un cambio mas.
+    async fn restore_etiquetas(
+        &mut self,
+        anteriores: &[EtiquetaCaja],
+        origen: &Origen,
+    ) -> anyhow::Result<u64>;
+
+    /// Ejecuta `CambiarEtiquetas` en modo prueba, devuelve las mercaderias que se cambiarian
+    /// sin modificarlas.
+    async fn query_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<Vec<Cambio>>;
+
+    /// Obtiene los ultimos `limit` cambios de etiqueta, del mas reciente al mas antiguo.
+    async fn query_historial(&mut self, limit: usize) -> anyhow::Result<Vec<RegistroCambio>>;
+
+    /// Indica si el usuario puede administrar la tabla intermedia.
+    async fn is_admin(&mut self, role: &str) -> anyhow::Result<bool>;
+
+    /// Obtiene la tabla intermedia completa, de todas las lineas y resoluciones.
+    async fn query_table_completa(&mut self) -> anyhow::Result<Vec<Etiquetas>>;
+
+    /// Agrega una etiqueta a la tabla intermedia, devuelve el id asignado.
+    async fn insert_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u8>;
+
+    /// Modifica una etiqueta de la tabla intermedia, identificada por su id.
+    async fn update_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u64>;
+
+    /// Borra una etiqueta de la tabla intermedia.
+    async fn delete_etiqueta(&mut self, id: u8) -> anyhow::Result<u64>;
+}
+
+/// Resultado de un cambio de etiqueta exitoso.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CambioAplicado {
+    /// Filas de `MercaderiasEtiquetaCaja` modificadas
+    pub rows: u64,
+    /// Etiqueta de cada mercaderia antes del cambio, permite deshacerlo
+    pub anteriores: Vec<EtiquetaCaja>,
+    /// Mercaderias que mantuvieron su etiqueta, vacio si se actualizaron todas
+    #[serde(default)]
+    pub faltantes: Vec<i32>,
+    /// Id con el que el servidor HTTP deshace el cambio, solo en sus respuestas
+    #[serde(default)]
+    pub deshacer: Option<u64>,
+    /// El cambio se aplico pero no se pudo leer el resultado, `rows` y `faltantes` no valen
+    #[serde(default)]
+    pub sin_confirmar: bool,
+}
+
+impl CambioAplicado {
+    /// Etiqueta que conservan las mercaderias que no se actualizaron.
+    pub fn sin_cambiar(&self) -> Vec<EtiquetaCaja> {
+        self.faltantes
+            .iter()
+            .map(|id| {
+                self.anteriores
+                    .iter()
+                    .find(|eti| eti.mercaderia_id == *id)
+                    .cloned()
+                    .unwrap_or(EtiquetaCaja {
+                        mercaderia_id: *id,
+                        etiqueta: None,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Puesto y usuario que hacen un cambio de etiqueta, para el registro de auditoria.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origen {
+    pub estacion: String,
+    pub usuario: String,
+}
+
+impl Origen {
+    /// Equipo y usuario de la sesion donde corre la aplicación.
+    pub fn local() -> Self {
+        Self {
+            estacion: estacion(),
+            usuario: usuario(),
+        }
+    }
+}
+
+/// Error lanzado por `CambiarEtiquetas` con su codigo (`THROW` o `RAISERROR`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcedureError {
+    pub code: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for ProcedureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ProcedureError {}
+
+/// Conecta con la base de datos indicada en la configuracion.
+pub async fn connect(config: &Config) -> anyhow::Result<Box<dyn Backend>> {
+    Ok(match config.backend {
+        #[cfg(not(target_arch = "wasm32"))]
+        BackendKind::Sql => Box::new(SQL::new_connection(&config.connection).await?),
+        #[cfg(target_arch = "wasm32")]
+        BackendKind::Sql => anyhow::bail!("La version web no puede conectarse a SQL Server."),
+        BackendKind::Memory => Box::new(Memory::default()),
+        BackendKind::Http => Box::new(Http::connect(&config.server_url).await?),
+    })
+}
+
+/// Indica si el error se debe a la perdida de la conexion, en cuyo caso hay que reconectar.
+pub fn is_connection_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        #[cfg(not(target_arch = "wasm32"))]
+        if matches!(
+            cause.downcast_ref::<tiberius::error::Error>(),
+            Some(
+                tiberius::error::Error::Io { .. }
+                    | tiberius::error::Error::Tls(_)
+                    | tiberius::error::Error::Routing { .. }
+            )
+        ) {
+            return true;
+        }
+
+        cause.is::<std::io::Error>()
+    })
+}
+
+/// Devuelve el codigo de error de la SP, si el error provino de ella.
+pub fn error_code(err: &anyhow::Error) -> Option<u32> {
+    err.downcast_ref::<ProcedureError>().map(|err| err.code)
+}
+
+/// Arma el registro de auditoria de un cambio de etiqueta.
+///
+/// `anteriores` son las etiquetas previas al cambio, `result` el resultado de la SP.
+pub fn registro<T>(
+    origen: &Origen,
+    anteriores: &[EtiquetaCaja],
+    etiqueta: &str,
+    result: &anyhow::Result<T>,
+) -> RegistroCambio {
+    RegistroCambio {
+        fecha: ahora(),
+        estacion: origen.estacion.clone(),
+        usuario: origen.usuario.clone(),
+        anterior: join_etiquetas(anteriores),
+        nueva: etiqueta.to_string(),
+        cantidad: anteriores.len() as i32,
+        codigo: match result {
+            Ok(_) => 0,
+            Err(err) => error_code(err).map_or(-1, |code| code as i32),
+        },
+    }
+}
+
+/// Etiquetas distintas de la lista separadas por comas, ordenadas alfabeticamente.
+pub fn join_etiquetas(etiquetas: &[EtiquetaCaja]) -> String {
+    let mut distintas: Vec<&str> = etiquetas.iter().map(EtiquetaCaja::nombre).collect();
+    distintas.sort_unstable();
+    distintas.dedup();
+    distintas.join(",")
+}
+
+/// Fecha y hora local, o UTC si no se puede obtener la zona horaria.
+pub fn ahora() -> time::PrimitiveDateTime {
+    ahora_local().unwrap_or_else(|| {
+        let now = time::OffsetDateTime::now_utc();
+        time::PrimitiveDateTime::new(now.date(), now.time())
+    })
+}
+
+/// Fecha y hora local, `None` si no se puede obtener la zona horaria. La primera vez queda en
+/// el log.
+pub fn ahora_local() -> Option<time::PrimitiveDateTime> {
+    static AVISO: std::sync::Once = std::sync::Once::new();
+
+    match time::OffsetDateTime::now_local() {
+        Ok(now) => Some(time::PrimitiveDateTime::new(now.date(), now.time())),
+        Err(err) => {
+            AVISO.call_once(|| {
+                error!(
+                    "No se pudo obtener la zona horaria local ({err}): las fechas quedan en UTC y \
+                    no se aplican los cambios programados."
+                )
+            });
+            None
+        }
+    }
+}
+
+/// Nombre del equipo donde corre la aplicación.
+pub fn estacion() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+/// Usuario de la sesion que corre la aplicación.
+pub fn usuario() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl Backend for SQL {
+    async fn query_table(
+        &mut self,
+        linea: Linea,
+        resolucion: Resolucion,
+    ) -> anyhow::Result<TablaEtiquetas> {
+        SQL::query_table(self, linea, resolucion).await
+    }
+
+    async fn query_ids(&mut self, linea: Linea) -> anyhow::Result<String> {
+        SQL::query_ids(self, linea).await
+    }
+
+    async fn query_etiquetas(&mut self, ids: &str) -> anyhow::Result<Vec<EtiquetaCaja>> {
+        SQL::query_etiquetas(self, ids).await
+    }
+
+    async fn execute_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+        origen: &Origen,
+    ) -> anyhow::Result<CambioAplicado> {
+        // Sin las etiquetas anteriores el cambio no se podria deshacer, no se aplica.
+        let anteriores = SQL::query_etiquetas(self, ids)
+            .await
+            .context("No se pudieron leer las etiquetas actuales, no se aplico el cambio.")?;
+
+        let result = SQL::execute_cambiar_etiquetas(self, ids, etiqueta)
+            .await
+            .map_err(|err| match err.downcast::<tiberius::error::Error>() {
+                Ok(err) => procedure_error(err),
+                Err(err) => err,
+            });
+
+        // Un fallo en la auditoria no debe revertir el cambio de etiqueta.
+        let registro = registro(origen, &anteriores, etiqueta, &result);
+        if let Err(err) = SQL::insert_registro(self, &registro).await {
+            error!("On sql::insert_registro: {err}");
+        }
+
+        result.map(|resultado| match resultado {
+            Some((rows, faltantes)) => CambioAplicado {
+                rows,
+                anteriores,
+                faltantes,
+                ..Default::default()
+            },
+            None => CambioAplicado {
+                anteriores,
+                sin_confirmar: true,
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn restore_etiquetas(
+        &mut self,
+        anteriores: &[EtiquetaCaja],
+        origen: &Origen,
+    ) -> anyhow::Result<u64> {
+        let ids: Vec<String> = anteriores
+            .iter()
+            .map(|eti| eti.mercaderia_id.to_string())
+            .collect();
+        // Sin las etiquetas actuales la auditoria no sabria que se deshizo.
+        let actuales = SQL::query_etiquetas(self, &ids.join(","))
+            .await
+            .context("No se pudieron leer las etiquetas actuales, no se deshizo el cambio.")?;
+
+        let result = SQL::restore_etiquetas(self, anteriores)
+            .await
+            .map(|result| result.total())
+            .map_err(procedure_error);
+
+        let registro = registro(origen, &actuales, &join_etiquetas(anteriores), &result);
+        if let Err(err) = SQL::insert_registro(self, &registro).await {
+            error!("On sql::insert_registro: {err}");
+        }
+
+        result
+    }
+
+    async fn query_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<Vec<Cambio>> {
+        SQL::query_cambiar_etiquetas(self, ids, etiqueta)
+            .await
+            .map_err(|err| match err.downcast::<tiberius::error::Error>() {
+                Ok(err) => procedure_error(err),
+                Err(err) => err,
+            })
+    }
+
+    async fn query_historial(&mut self, limit: usize) -> anyhow::Result<Vec<RegistroCambio>> {
+        SQL::query_historial(self, limit).await
+    }
+
+    async fn is_admin(&mut self, role: &str) -> anyhow::Result<bool> {
+        SQL::is_admin(self, role).await
+    }
+
+    async fn query_table_completa(&mut self) -> anyhow::Result<Vec<Etiquetas>> {
+        SQL::query_table_completa(self).await
+    }
+
+    async fn insert_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u8> {
+        SQL::insert_etiqueta(self, etiqueta).await
+    }
+
+    async fn update_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u64> {
+        SQL::update_etiqueta(self, etiqueta).await
+    }
+
+    async fn delete_etiqueta(&mut self, id: u8) -> anyhow::Result<u64> {
+        SQL::delete_etiqueta(self, id).await
+    }
+}
+
+/// Convierte los errores lanzados por la SP en [`ProcedureError`].
+#[cfg(not(target_arch = "wasm32"))]
+fn procedure_error(err: tiberius::error::Error) -> anyhow::Error {
+    match err {
+        tiberius::error::Error::Server(err) => ProcedureError {
+            code: err.code(),
+            message: err.message().to_string(),
+        }
+        .into(),
+        err => err.into(),
+    }
+}
@@ -0,0 +1,220 @@
+//! Backend que opera a traves del servidor HTTP (`faena_cli serve`). Lo usa la version web,
+//! que desde el navegador no puede conectarse a SQL Server.
+
+use super::{Backend, CambioAplicado, Origen, ProcedureError};
+use crate::{
+    constants,
+    sql::{Cambio, EtiquetaCaja, Etiquetas, Linea, RegistroCambio, Resolucion, TablaEtiquetas},
+};
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io;
+
+/// Encabezado con el nombre del equipo que hace el pedido, el servidor lo registra junto con
+/// la IP del cliente.
+pub const ESTACION_HEADER: &str = "X-Estacion";
+/// Encabezado con el usuario que hace el pedido.
+pub const USUARIO_HEADER: &str = "X-Usuario";
+
+/// Cuerpo de `POST /cambios`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CambioRequest {
+    pub etiqueta: String,
+    /// Lista de mercaderias separada por comas, por defecto todas las medias
+    #[serde(default)]
+    pub ids: Option<String>,
+    /// Ejecuta la SP en modo prueba, sin modificar las etiquetas
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Cuerpo de `POST /cambios/deshacer`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeshacerRequest {
+    /// `deshacer` del [`CambioAplicado`] que devolvio el servidor
+    pub id: u64,
+}
+
+/// Cuerpo de las respuestas de error del servidor.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// Codigo de error de la SP, si fallo ella
+    pub code: Option<u32>,
+    pub error: String,
+}
+
+/// Cliente del servidor HTTP.
+#[derive(Debug, Clone)]
+pub struct Http {
+    /// URL base del servidor, sin la barra final
+    url: String,
+    /// Ultimo cambio aplicado, el servidor lo deshace por su id
+    ultimo: Option<(u64, Vec<EtiquetaCaja>)>,
+}
+
+impl Http {
+    /// Verifica que el servidor responda y que tenga conexion con la base de datos.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let http = Self {
+            url: url.trim_end_matches('/').to_string(),
+            ultimo: None,
+        };
+        http.get::<Vec<i32>>("/medias").await?;
+
+        Ok(http)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        self.fetch(ehttp::Request::get(format!("{}{path}", self.url)))
+            .await
+    }
+
+    /// Los `POST` modifican etiquetas, llevan el origen para el registro de auditoria.
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+        origen: &Origen,
+    ) -> anyhow::Result<T> {
+        let mut request = ehttp::Request::json(format!("{}{path}", self.url), body)?;
+        request.headers.insert(ESTACION_HEADER, &origen.estacion);
+        request.headers.insert(USUARIO_HEADER, &origen.usuario);
+        self.fetch(request).await
+    }
+
+    /// Envia el pedido y convierte las respuestas de error en los mismos errores que devuelve
+    /// el backend de SQL Server.
+    async fn fetch<T: DeserializeOwned>(&self, request: ehttp::Request) -> anyhow::Result<T> {
+        let (tx, rx) = async_std::channel::bounded(1);
+        ehttp::fetch(request, move |result| {
+            let _ = tx.try_send(result);
+        });
+
+        // Un fallo de red se trata como perdida de la conexion, asi el worker reconecta.
+        let response = rx
+            .recv()
+            .await?
+            .map_err(|err| io::Error::new(io::ErrorKind::ConnectionRefused, err))?;
+        if response.ok {
+            return Ok(response.json()?);
+        }
+
+        let error: Option<ErrorResponse> = response.json().ok();
+        let message = error
+            .as_ref()
+            .map_or_else(|| response.status_text.clone(), |err| err.error.clone());
+
+        Err(match error.and_then(|err| err.code) {
+            Some(code) => ProcedureError { code, message }.into(),
+            // El servidor perdio la conexion con SQL Server.
+            None if response.status == 503 => {
+                io::Error::new(io::ErrorKind::NotConnected, message).into()
+            }
+            None => anyhow::anyhow!("{} {message}", response.status),
+        })
+    }
+}
+
+#[async_trait]
+impl Backend for Http {
+    async fn query_table(
+        &mut self,
+        linea: Linea,
+        resolucion: Resolucion,
+    ) -> anyhow::Result<TablaEtiquetas> {
+        self.get(&format!(
+            "/etiquetas?linea={linea}&dpi={}",
+            resolucion.dpi()
+        ))
+        .await
+    }
+
+    async fn query_ids(&mut self, linea: Linea) -> anyhow::Result<String> {
+        let ids: Vec<i32> = self
+            .get(&format!("/medias?linea={}", linea.as_str()))
+            .await?;
+        let ids: Vec<String> = ids.iter().map(i32::to_string).collect();
+
+        anyhow::ensure!(!ids.is_empty(), "La query 'ListarMedias' esta vacia.");
+        Ok(ids.join(","))
+    }
+
+    async fn query_etiquetas(&mut self, ids: &str) -> anyhow::Result<Vec<EtiquetaCaja>> {
+        self.get(&format!("/medias/etiquetas?ids={ids}")).await
+    }
+
+    async fn execute_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+        origen: &Origen,
+    ) -> anyhow::Result<CambioAplicado> {
+        let request = CambioRequest {
+            etiqueta: etiqueta.to_string(),
+            ids: Some(ids.to_string()),
+            dry_run: false,
+        };
+        let cambio: CambioAplicado = self.post("/cambios", &request, origen).await?;
+
+        self.ultimo = cambio.deshacer.map(|id| (id, cambio.anteriores.clone()));
+        Ok(cambio)
+    }
+
+    /// El servidor solo vuelve a las etiquetas que registro el, asi que se puede deshacer el
+    /// ultimo cambio hecho con esta conexion.
+    async fn restore_etiquetas(
+        &mut self,
+        anteriores: &[EtiquetaCaja],
+        origen: &Origen,
+    ) -> anyhow::Result<u64> {
+        let id = match &self.ultimo {
+            Some((id, ultimos)) if ultimos == anteriores => *id,
+            _ => anyhow::bail!("A traves del servidor solo se puede deshacer el ultimo cambio."),
+        };
+
+        let rows = self
+            .post("/cambios/deshacer", &DeshacerRequest { id }, origen)
+            .await?;
+        self.ultimo = None;
+        Ok(rows)
+    }
+
+    async fn query_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<Vec<Cambio>> {
+        let request = CambioRequest {
+            etiqueta: etiqueta.to_string(),
+            ids: Some(ids.to_string()),
+            dry_run: true,
+        };
+        self.post("/cambios", &request, &Origen::local()).await
+    }
+
+    async fn query_historial(&mut self, limit: usize) -> anyhow::Result<Vec<RegistroCambio>> {
+        self.get(&format!("/historial?limit={limit}")).await
+    }
+
+    // La administracion requiere el rol del usuario en SQL Server, a traves del servidor se
+    // veria el del usuario del servicio.
+    async fn is_admin(&mut self, _role: &str) -> anyhow::Result<bool> {
+        anyhow::bail!(constants::ADMIN_HTTP_MSG)
+    }
+
+    async fn query_table_completa(&mut self) -> anyhow::Result<Vec<Etiquetas>> {
+        anyhow::bail!(constants::ADMIN_HTTP_MSG)
+    }
+
+    async fn insert_etiqueta(&mut self, _etiqueta: &Etiquetas) -> anyhow::Result<u8> {
+        anyhow::bail!(constants::ADMIN_HTTP_MSG)
+    }
+
+    async fn update_etiqueta(&mut self, _etiqueta: &Etiquetas) -> anyhow::Result<u64> {
+        anyhow::bail!(constants::ADMIN_HTTP_MSG)
+    }
+
+    async fn delete_etiqueta(&mut self, _id: u8) -> anyhow::Result<u64> {
+        anyhow::bail!(constants::ADMIN_HTTP_MSG)
+    }
+}
@@ -0,0 +1,729 @@
+//! Version en memoria de la base de datos, para desarrollar y probar la interfaz sin SQL Server.
+//! Replica el comportamiento de las SPs de `sql/deploy_AuxiliarFaena.sql`.
+
+use super::{Backend, CambioAplicado, Origen, ProcedureError};
+use crate::{
+    constants,
+    sql::{Cambio, EtiquetaCaja, Etiquetas, Linea, RegistroCambio, Resolucion, TablaEtiquetas},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+
+/// Fila de `Mercaderias` junto con su fila en `MercaderiasEtiquetaCaja`.
+#[derive(Debug, Clone)]
+pub struct Mercaderia {
+    pub id: i32,
+    /// `MercaderiaTipo_Id`, 1 es media.
+    pub tipo: i32,
+    pub activo: bool,
+    /// `sEtiqueta` de `MercaderiasEtiquetaCaja`, `None` si la mercaderia no tiene fila.
+    pub etiqueta: Option<String>,
+    /// Simula una fila que rechaza el `UPDATE`, para reproducir el error 56450.
+    pub bloqueada: bool,
+}
+
+/// Base de datos en memoria.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    /// `[cambiarEtiquetas].[FaenaEtiquetas]`
+    pub table: Vec<Etiquetas>,
+    /// `[cambiarEtiquetas].[Lineas]`, el `MercaderiaTipo_Id` de cada linea
+    pub lineas: Vec<(Linea, i32)>,
+    /// `[configuracion].[Mercaderias]`
+    pub mercaderias: Vec<Mercaderia>,
+    /// `[cambiarEtiquetas].[CambiosEtiqueta]`, del mas antiguo al mas reciente
+    pub historial: Vec<RegistroCambio>,
+}
+
+impl Default for Memory {
+    /// Mismos datos que carga el script de deploy y las pruebas de la SP.
+    fn default() -> Self {
+        let table = [
+            ("faena_h_aa_2copias", "H AA 2", "#664980"),
+            ("faena_h_aa_3copias", "H AA 3", "#4C709A"),
+            ("faena_h_aa_4copias", "H AA 4", "#567556"),
+            ("faena_hilton_2copias", "Hilton 2", "#B7AA5E"),
+            ("faena_hilton_3copias", "Hilton 3", "#A66A40"),
+            ("faena_hilton_4copias", "Hilton 4", "#9B4244"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (etiqueta, label, color))| Etiquetas {
+            id: i as u8 + 1,
+            enable: true,
+            etiqueta: etiqueta.to_string(),
+            label: label.to_string(),
+            color: color.to_string(),
+            dpi: Resolucion::Dpi300,
+            linea: Linea::Faena,
+            hotkey: None,
+        })
+        .collect();
+
+        let mercaderias = (11..=19)
+            .chain([95])
+            .chain(398..=406)
+            .map(|id| Mercaderia {
+                id,
+                tipo: 1,
+                activo: true,
+                etiqueta: Some("faena_h_aa_2copias".to_string()),
+                bloqueada: false,
+            })
+            .collect();
+
+        Self {
+            table,
+            lineas: vec![(Linea::Faena, 1)],
+            mercaderias,
+            historial: Vec::new(),
+        }
+    }
+}
+
+impl Memory {
+    /// Replica las validaciones de `CambiarEtiquetas`, comunes al modo prueba y producción.
+    ///
+    /// Devuelve la lista de ids parseada y la posicion de las mercaderias activas a actualizar.
+    fn mercaderia_activa(
+        &self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<(Vec<i32>, Vec<usize>)> {
+        if ids.trim().is_empty() {
+            return Err(Memory::raiserror("La variable @mercaderia esta vacia"));
+        }
+        if etiqueta.is_empty() {
+            return Err(Memory::raiserror("La variable @etiqueta esta vacia"));
+        }
+
+        // SplitToList
+        let list = ids
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ProcedureError {
+                code: constants::CONVERSION_CODE,
+                message: format!("Conversion failed when converting '{ids}' to data type int."),
+            })?;
+
+        // Una etiqueta inexistente no se considera deshabilitada, igual que en la SP.
+        if self
+            .table
+            .iter()
+            .any(|eti| eti.etiqueta == etiqueta && !eti.enable)
+        {
+            return Err(ProcedureError {
+                code: constants::WARN_CODE,
+                message: format!("Etiqueta {etiqueta} no habilitada para producción."),
+            }
+            .into());
+        }
+
+        // Filtra la lista de mercaderia para solo las activas
+        let activas: Vec<usize> = self
+            .mercaderias
+            .iter()
+            .enumerate()
+            .filter(|(_, merc)| list.contains(&merc.id) && merc.activo && merc.etiqueta.is_some())
+            .map(|(i, _)| i)
+            .collect();
+
+        if activas.is_empty() {
+            return Err(ProcedureError {
+                code: constants::MISSING_CODE,
+                message: format!("Mercaderia ({ids}) no existe"),
+            }
+            .into());
+        }
+
+        // La etiqueta tiene que ser de la linea de las mercaderias
+        let linea = self
+            .table
+            .iter()
+            .find(|eti| eti.etiqueta == etiqueta)
+            .map(|eti| eti.linea);
+        if let Some(linea) = linea {
+            let tipo = self.tipo(linea);
+            if activas
+                .iter()
+                .any(|&i| Some(self.mercaderias[i].tipo) != tipo)
+            {
+                return Err(ProcedureError {
+                    code: constants::LINEA_CODE,
+                    message: format!(
+                        "Etiqueta {etiqueta} de la linea {}, no corresponde a la mercaderia.",
+                        linea.as_str()
+                    ),
+                }
+                .into());
+            }
+        }
+
+        Ok((list, activas))
+    }
+
+    /// `CambiarEtiquetas` en modo producción, devuelve las filas actualizadas y las mercaderias
+    /// que mantuvieron su etiqueta.
+    fn cambiar_etiquetas(&mut self, ids: &str, etiqueta: &str) -> anyhow::Result<(u64, Vec<i32>)> {
+        let (list, activas) = self.mercaderia_activa(ids, etiqueta)?;
+
+        // Las mercaderias de la lista que no existen, estan inactivas o no tienen etiqueta
+        let mut faltantes: Vec<i32> = list
+            .iter()
+            .copied()
+            .filter(|id| !activas.iter().any(|&i| self.mercaderias[i].id == *id))
+            .collect();
+
+        let mut rows = 0;
+        for i in activas {
+            let merc = &mut self.mercaderias[i];
+            if merc.bloqueada {
+                if merc.etiqueta.as_deref() != Some(etiqueta) {
+                    faltantes.push(merc.id);
+                }
+            } else {
+                merc.etiqueta = Some(etiqueta.to_string());
+                rows += 1;
+            }
+        }
+
+        faltantes.sort_unstable();
+        faltantes.dedup();
+        let mut distintas = list.clone();
+        distintas.sort_unstable();
+        distintas.dedup();
+        if faltantes.len() == distintas.len() {
+            return Err(ProcedureError {
+                code: constants::UPDATE_ERROR_CODE,
+                message: format!(
+                    "No se han podido actualizar esta mercaderia: {}",
+                    faltantes
+                        .iter()
+                        .map(i32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+            }
+            .into());
+        }
+
+        Ok((rows, faltantes))
+    }
+
+    /// `MercaderiaTipo_Id` de la linea.
+    fn tipo(&self, linea: Linea) -> Option<i32> {
+        self.lineas
+            .iter()
+            .find(|(otra, _)| *otra == linea)
+            .map(|(_, tipo)| *tipo)
+    }
+
+    /// Replica el indice unico `IX_Unique_FaenaEtiquetas`.
+    fn etiqueta_unica(&self, etiqueta: &Etiquetas) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !self
+                .table
+                .iter()
+                .any(|eti| eti.id != etiqueta.id && eti.etiqueta == etiqueta.etiqueta),
+            "Ya existe la etiqueta {}.",
+            etiqueta.etiqueta
+        );
+        Ok(())
+    }
+
+    /// Error de `RAISERROR` con mensaje, SQL Server le asigna el codigo 50000.
+    fn raiserror(message: &str) -> anyhow::Error {
+        ProcedureError {
+            code: constants::RAISERROR_CODE,
+            message: message.to_string(),
+        }
+        .into()
+    }
+}
+
+#[async_trait]
+impl Backend for Memory {
+    async fn query_table(
+        &mut self,
+        linea: Linea,
+        resolucion: Resolucion,
+    ) -> anyhow::Result<TablaEtiquetas> {
+        Ok(self
+            .table
+            .iter()
+            .filter(|eti| eti.linea == linea && eti.dpi == resolucion)
+            .cloned()
+            .map(Ok)
+            .collect())
+    }
+
+    async fn query_ids(&mut self, linea: Linea) -> anyhow::Result<String> {
+        let tipo = self.tipo(linea).with_context(|| {
+            format!(
+                "La linea '{}' no esta en la tabla Lineas, hay que agregarla con su MercaderiaTipo_Id.",
+                linea.as_str()
+            )
+        })?;
+        let ids: Vec<String> = self
+            .mercaderias
+            .iter()
+            .filter(|merc| merc.tipo == tipo)
+            .map(|merc| merc.id.to_string())
+            .collect();
+
+        anyhow::ensure!(!ids.is_empty(), "La query 'ListarMedias' esta vacia.");
+        Ok(ids.join(","))
+    }
+
+    async fn query_etiquetas(&mut self, ids: &str) -> anyhow::Result<Vec<EtiquetaCaja>> {
+        let list: Vec<i32> = ids
+            .split(',')
+            .filter_map(|item| item.trim().parse().ok())
+            .collect();
+
+        Ok(self
+            .mercaderias
+            .iter()
+            .filter(|merc| list.contains(&merc.id))
+            .filter_map(|merc| {
+                merc.etiqueta.as_ref().map(|etiqueta| EtiquetaCaja {
+                    mercaderia_id: merc.id,
+                    etiqueta: Some(etiqueta.clone()),
+                })
+            })
+            .collect())
+    }
+
+    async fn execute_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+        origen: &Origen,
+    ) -> anyhow::Result<CambioAplicado> {
+        let anteriores = self.query_etiquetas(ids).await?;
+        let result = self.cambiar_etiquetas(ids, etiqueta);
+
+        self.historial
+            .push(super::registro(origen, &anteriores, etiqueta, &result));
+        result.map(|(rows, faltantes)| CambioAplicado {
+            rows,
+            anteriores,
+            faltantes,
+            ..Default::default()
+        })
+    }
+
+    async fn restore_etiquetas(
+        &mut self,
+        anteriores: &[EtiquetaCaja],
+        origen: &Origen,
+    ) -> anyhow::Result<u64> {
+        let mut actuales = Vec::new();
+        let mut rows = 0;
+
+        for anterior in anteriores {
+            let merc = self
+                .mercaderias
+                .iter_mut()
+                .find(|merc| merc.id == anterior.mercaderia_id && merc.etiqueta.is_some());
+
+            if let Some(merc) = merc {
+                actuales.push(EtiquetaCaja {
+                    mercaderia_id: merc.id,
+                    etiqueta: std::mem::replace(&mut merc.etiqueta, anterior.etiqueta.clone()),
+                });
+                rows += 1;
+            }
+        }
+
+        let result = Ok(rows);
+        self.historial.push(super::registro(
+            origen,
+            &actuales,
+            &super::join_etiquetas(anteriores),
+            &result,
+        ));
+        result
+    }
+
+    async fn query_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<Vec<Cambio>> {
+        let (_, activas) = self.mercaderia_activa(ids, etiqueta)?;
+
+        Ok(activas
+            .into_iter()
+            .map(|i| Cambio {
+                mercaderia_id: self.mercaderias[i].id,
+                actual: self.mercaderias[i].etiqueta.clone().unwrap_or_default(),
+                nueva: etiqueta.to_string(),
+            })
+            .collect())
+    }
+
+    async fn query_historial(&mut self, limit: usize) -> anyhow::Result<Vec<RegistroCambio>> {
+        Ok(self.historial.iter().rev().take(limit).cloned().collect())
+    }
+
+    async fn is_admin(&mut self, _role: &str) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn query_table_completa(&mut self) -> anyhow::Result<Vec<Etiquetas>> {
+        Ok(self.table.clone())
+    }
+
+    async fn insert_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u8> {
+        etiqueta.validar()?;
+        // `[id]` es TINYINT, igual que el IDENTITY no pasa de 255.
+        let id = self
+            .table
+            .iter()
+            .map(|eti| eti.id)
+            .max()
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("No quedan ids disponibles para agregar etiquetas."))?;
+        let etiqueta = Etiquetas {
+            id,
+            label: etiqueta.label.trim().to_string(),
+            color: etiqueta.color.to_uppercase(),
+            ..etiqueta.clone()
+        };
+        self.etiqueta_unica(&etiqueta)?;
+        etiqueta.validar_hotkey(&self.table)?;
+
+        self.table.push(etiqueta);
+        Ok(id)
+    }
+
+    async fn update_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u64> {
+        etiqueta.validar()?;
+        self.etiqueta_unica(etiqueta)?;
+        etiqueta.validar_hotkey(&self.table)?;
+
+        let fila = self
+            .table
+            .iter_mut()
+            .find(|eti| eti.id == etiqueta.id)
+            .ok_or_else(|| anyhow::anyhow!("La etiqueta {} no existe.", etiqueta.id))?;
+        *fila = Etiquetas {
+            label: etiqueta.label.trim().to_string(),
+            color: etiqueta.color.to_uppercase(),
+            ..etiqueta.clone()
+        };
+
+        Ok(1)
+    }
+
+    async fn delete_etiqueta(&mut self, id: u8) -> anyhow::Result<u64> {
+        let len = self.table.len();
+        self.table.retain(|eti| eti.id != id);
+        anyhow::ensure!(self.table.len() < len, "La etiqueta {id} no existe.");
+
+        Ok(1)
+    }
+}
+
+#[async_std::test]
+async fn test_memory_cambiar_etiquetas() {
+    let mut memory = Memory::default();
+    let ids = memory.query_ids(Linea::Faena).await.unwrap();
+
+    let rows = memory
+        .execute_cambiar_etiquetas(&ids, "faena_hilton_3copias", &Origen::local())
+        .await
+        .unwrap()
+        .rows;
+
+    assert_eq!(rows, memory.mercaderias.len() as u64);
+    assert!(memory
+        .mercaderias
+        .iter()
+        .all(|merc| merc.etiqueta.as_deref() == Some("faena_hilton_3copias")));
+
+    let historial = memory.query_historial(10).await.unwrap();
+    assert_eq!(historial.len(), 1);
+    assert_eq!(historial[0].anterior, "faena_h_aa_2copias");
+    assert_eq!(historial[0].cantidad, rows as i32);
+    assert_eq!(historial[0].codigo, 0);
+}
+
+#[async_std::test]
+async fn test_memory_cambio_parcial() {
+    let mut memory = Memory::default();
+    // 12 inactiva, 13 sin etiqueta de caja, 14 no se deja actualizar y 999 no existe.
+    memory.mercaderias[1].activo = false;
+    memory.mercaderias[2].etiqueta = None;
+    memory.mercaderias[3].bloqueada = true;
+
+    let cambio = memory
+        .execute_cambiar_etiquetas(
+            "11,12,13,14,999,11",
+            "faena_hilton_3copias",
+            &Origen::local(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(cambio.rows, 1);
+    assert_eq!(cambio.faltantes, vec![12, 13, 14, 999]);
+    assert_eq!(
+        memory.mercaderias[1].etiqueta.as_deref(),
+        Some("faena_h_aa_2copias")
+    );
+    assert!(cambio.sin_cambiar().contains(&EtiquetaCaja {
+        mercaderia_id: 14,
+        etiqueta: Some("faena_h_aa_2copias".to_string()),
+    }));
+
+    // Si ninguna se actualiza la SP falla aunque alguna este activa.
+    let err = memory
+        .execute_cambiar_etiquetas("12,14", "faena_hilton_3copias", &Origen::local())
+        .await
+        .unwrap_err();
+    assert_eq!(super::error_code(&err), Some(constants::UPDATE_ERROR_CODE));
+}
+
+#[async_std::test]
+async fn test_memory_estacion() {
+    let mut memory = Memory::default();
+    memory.mercaderias.push(Mercaderia {
+        id: 500,
+        tipo: 2,
+        activo: true,
+        etiqueta: Some("cuarteo_delantero".to_string()),
+        bloqueada: false,
+    });
+    memory.table[0].linea = Linea::Cuarteo;
+    memory.lineas.push((Linea::Cuarteo, 2));
+
+    // Cada linea solo ve sus etiquetas y su tipo de mercaderia.
+    let faena = memory
+        .query_table(Linea::Faena, Resolucion::Dpi300)
+        .await
+        .unwrap();
+    assert_eq!(faena.filas.len(), 5);
+    let cuarteo = memory
+        .query_table(Linea::Cuarteo, Resolucion::Dpi300)
+        .await
+        .unwrap();
+    assert_eq!(cuarteo.filas, vec![memory.table[0].clone()]);
+
+    assert_eq!(memory.query_ids(Linea::Cuarteo).await.unwrap(), "500");
+    assert!(!memory
+        .query_ids(Linea::Faena)
+        .await
+        .unwrap()
+        .contains("500"));
+    // Una linea sin fila en Lineas no pasa por una linea sin medias.
+    let err = memory.query_ids(Linea::Despostada).await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("'despostada' no esta en la tabla Lineas"));
+
+    // La SP rechaza las etiquetas de otra linea que la mercaderia.
+    let etiqueta = memory.table[0].etiqueta.clone();
+    for ids in ["11", "11,500"] {
+        let err = memory
+            .execute_cambiar_etiquetas(ids, &etiqueta, &Origen::local())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            super::error_code(&err),
+            Some(constants::LINEA_CODE),
+            "{ids}"
+        );
+    }
+    memory
+        .execute_cambiar_etiquetas("500", &etiqueta, &Origen::local())
+        .await
+        .unwrap();
+    assert!(memory
+        .query_cambiar_etiquetas("500", "faena_hilton_2copias")
+        .await
+        .is_err());
+}
+
+#[async_std::test]
+async fn test_memory_filas_rechazadas() {
+    let mut memory = Memory::default();
+    memory.table[1].color = "#GGG".to_string();
+    memory.table[4].label = String::new();
+
+    // Las filas invalidas no llegan a la grilla, quedan para el diagnostico.
+    let tabla = memory
+        .query_table(Linea::Faena, Resolucion::Dpi300)
+        .await
+        .unwrap();
+    assert_eq!(tabla.filas.len(), 4);
+    let rechazadas: Vec<Option<u8>> = tabla.rechazadas.iter().map(|fila| fila.id).collect();
+    assert_eq!(rechazadas, vec![Some(2), Some(5)]);
+    assert_eq!(tabla.rechazadas[0].motivo, crate::sql::MotivoRechazo::Color);
+
+    // La administracion las muestra igual para poder corregirlas.
+    assert_eq!(memory.query_table_completa().await.unwrap().len(), 6);
+}
+
+#[async_std::test]
+async fn test_memory_error_codes() {
+    let mut memory = Memory::default();
+    memory.table[0].enable = false;
+    let etiqueta = memory.table[0].etiqueta.clone();
+
+    let err = memory
+        .execute_cambiar_etiquetas("11", &etiqueta, &Origen::local())
+        .await;
+    assert_eq!(
+        super::error_code(&err.unwrap_err()),
+        Some(constants::WARN_CODE)
+    );
+
+    let err = memory
+        .execute_cambiar_etiquetas("-1", "faena_hilton_2copias", &Origen::local())
+        .await;
+    assert_eq!(
+        super::error_code(&err.unwrap_err()),
+        Some(constants::MISSING_CODE)
+    );
+
+    memory.mercaderias[0].bloqueada = true;
+    let err = memory
+        .execute_cambiar_etiquetas("11", "faena_hilton_2copias", &Origen::local())
+        .await;
+    assert_eq!(
+        super::error_code(&err.unwrap_err()),
+        Some(constants::UPDATE_ERROR_CODE)
+    );
+
+    let err = memory
+        .execute_cambiar_etiquetas("", "faena_hilton_2copias", &Origen::local())
+        .await;
+    assert_eq!(
+        super::error_code(&err.unwrap_err()),
+        Some(constants::RAISERROR_CODE)
+    );
+}
+
+#[async_std::test]
+async fn test_memory_modo_prueba() {
+    let mut memory = Memory::default();
+
+    let cambios = memory
+        .query_cambiar_etiquetas("11,12,-1", "faena_hilton_4copias")
+        .await
+        .unwrap();
+
+    assert_eq!(cambios.len(), 2);
+    assert!(cambios
+        .iter()
+        .all(|cambio| cambio.actual == "faena_h_aa_2copias"
+            && cambio.nueva == "faena_hilton_4copias"));
+    // El modo prueba no modifica la mercaderia
+    assert_eq!(
+        memory.mercaderias[0].etiqueta.as_deref(),
+        Some("faena_h_aa_2copias")
+    );
+}
+
+#[async_std::test]
+async fn test_memory_deshacer() {
+    let mut memory = Memory::default();
+    memory.mercaderias[0].etiqueta = Some("faena_hilton_2copias".to_string());
+    let antes: Vec<Option<String>> = memory
+        .mercaderias
+        .iter()
+        .map(|m| m.etiqueta.clone())
+        .collect();
+
+    let ids = memory.query_ids(Linea::Faena).await.unwrap();
+    let cambio = memory
+        .execute_cambiar_etiquetas(&ids, "faena_hilton_4copias", &Origen::local())
+        .await
+        .unwrap();
+    memory
+        .restore_etiquetas(&cambio.anteriores, &Origen::local())
+        .await
+        .unwrap();
+
+    let despues: Vec<Option<String>> = memory
+        .mercaderias
+        .iter()
+        .map(|m| m.etiqueta.clone())
+        .collect();
+    assert_eq!(antes, despues);
+    assert_eq!(memory.historial.len(), 2);
+}
+
+#[async_std::test]
+async fn test_memory_admin() {
+    let mut memory = Memory::default();
+    let mut nueva = Etiquetas {
+        id: 0,
+        enable: false,
+        etiqueta: "faena_cuota_2copias".to_string(),
+        label: "Cuota 2".to_string(),
+        color: "#3a5f7d".to_string(),
+        dpi: Resolucion::Dpi600,
+        linea: Linea::Cuarteo,
+        hotkey: Some("F1".to_string()),
+    };
+
+    nueva.id = memory.insert_etiqueta(&nueva).await.unwrap();
+    assert_eq!(nueva.id, 7);
+    // El indice unico no admite la misma etiqueta dos veces
+    assert!(memory.insert_etiqueta(&nueva).await.is_err());
+
+    nueva.enable = true;
+    nueva.label = "Cuota 2 copias".to_string();
+    memory.update_etiqueta(&nueva).await.unwrap();
+    let table = memory
+        .query_table(Linea::Cuarteo, Resolucion::Dpi600)
+        .await
+        .unwrap();
+    let fila = table.filas.iter().find(|eti| eti.id == nueva.id).unwrap();
+    assert!(fila.enable);
+    assert_eq!(fila.color, "#3A5F7D");
+
+    nueva.label = "Cuota de dos copias".to_string();
+    assert!(memory.update_etiqueta(&nueva).await.is_err());
+
+    // Otra etiqueta de la linea y resolucion no puede usar la misma tecla
+    let otra = Etiquetas {
+        etiqueta: "faena_cuota_4copias".to_string(),
+        label: "Cuota 4".to_string(),
+        ..nueva.clone()
+    };
+    assert!(otra.validar().is_ok());
+    assert!(memory.insert_etiqueta(&otra).await.is_err());
+    let otra = Etiquetas {
+        hotkey: Some("F2".to_string()),
+        ..otra
+    };
+    let id = memory.insert_etiqueta(&otra).await.unwrap();
+    assert!(memory
+        .update_etiqueta(&Etiquetas {
+            id,
+            hotkey: Some("F1".to_string()),
+            ..otra
+        })
+        .await
+        .is_err());
+    memory.delete_etiqueta(id).await.unwrap();
+
+    memory.delete_etiqueta(nueva.id).await.unwrap();
+    assert!(memory.delete_etiqueta(nueva.id).await.is_err());
+    assert_eq!(memory.query_table_completa().await.unwrap().len(), 6);
+
+    // Los ids se agotan en 255, como en la tabla.
+    memory.table[5].id = u8::MAX;
+    nueva.etiqueta = "faena_cuota_3copias".to_string();
+    nueva.label = "Cuota 3".to_string();
+    assert!(nueva.validar().is_ok());
+    assert!(memory.insert_etiqueta(&nueva).await.is_err());
+}
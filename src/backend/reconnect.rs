@@ -0,0 +1,66 @@
+//! Reconexion con la base de datos con backoff exponencial.
+
+use super::{connect, Backend};
+use crate::config::Config;
+use std::time::Duration;
+use tracing::error;
+use web_time::Instant;
+
+/// Estado de la conexion con la base de datos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Intentando conectar, `attempt` es la cantidad de intentos fallidos hasta ahora
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Sin conexion, el proximo intento es en `retry_at`
+    Offline {
+        attempt: u32,
+        retry_at: Instant,
+    },
+}
+
+/// Intenta conectar hasta lograrlo, duplicando la espera entre intentos hasta
+/// `reconnect_max` segundos. Cada intento se informa con `on_state`; el estado `Connected` lo
+/// informa quien recibe la conexion, cuando ya puede atender pedidos.
+pub async fn reconnect(config: &Config, on_state: impl Fn(ConnectionState)) -> Box<dyn Backend> {
+    let max = Duration::from_secs(config.connection.reconnect_max);
+    let mut backoff = Duration::from_secs(config.connection.reconnect_initial).min(max);
+    let mut attempt = 0;
+
+    loop {
+        on_state(ConnectionState::Reconnecting { attempt });
+
+        match connect(config).await {
+            Ok(backend) => return backend,
+            Err(err) => {
+                attempt += 1;
+                error!("On sql::new_connection (intento {attempt}): {err}");
+
+                on_state(ConnectionState::Offline {
+                    attempt,
+                    retry_at: Instant::now() + backoff,
+                });
+                async_std::task::sleep(backoff).await;
+                backoff = (backoff * 2).min(max);
+            }
+        }
+    }
+}
+
+#[async_std::test]
+async fn test_reconnect_memory() {
+    let config = Config {
+        backend: crate::config::BackendKind::Memory,
+        ..Default::default()
+    };
+    let states = std::sync::Mutex::new(Vec::new());
+
+    reconnect(&config, |state| states.lock().unwrap().push(state)).await;
+
+    assert_eq!(
+        *states.lock().unwrap(),
+        [ConnectionState::Reconnecting { attempt: 0 }]
+    );
+}
@@ -0,0 +1,348 @@
+//! Cambio de etiquetas desde la linea de comandos, para tareas programadas y otras herramientas.
+//!
+//! Usa la misma configuracion que la interfaz. Si falla, el codigo de salida indica el motivo:
+//! en Windows es el codigo del error de la SP (ver `sql/deploy_AuxiliarFaena.sql`), en el resto
+//! cada error de la SP tiene uno propio de un solo byte.
+
+use clap::{Parser, Subcommand};
+use faena_etiquetas::{
+    backend::{self, Backend, Origen},
+    config::{self, BackendKind, Config},
+    constants, server, EtiquetaCaja, Linea, Migracion, Resolucion, SQL,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, path::PathBuf};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Cambia las etiquetas de faena sin abrir la interfaz")]
+struct Cli {
+    /// Salida en JSON en lugar de texto
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Comando,
+}
+
+#[derive(Debug, Subcommand)]
+enum Comando {
+    /// Lista la tabla intermedia de etiquetas
+    List {
+        /// Linea de produccion (faena, cuarteo o despostada), por defecto la de la configuracion
+        #[arg(long)]
+        linea: Option<String>,
+        /// Resolucion de las etiquetas (203, 300 o 600), por defecto la de la configuracion
+        #[arg(long, value_parser = ["203", "300", "600"])]
+        dpi: Option<String>,
+    },
+    /// Lista las mercaderias de la linea
+    Medias {
+        /// Linea de produccion (faena, cuarteo o despostada), por defecto la de la configuracion
+        #[arg(long)]
+        linea: Option<String>,
+    },
+    /// Aplica una etiqueta a las medias
+    Set {
+        etiqueta: String,
+        /// Solo informa las mercaderias que cambiarian (`@prueba = 0`)
+        #[arg(long)]
+        dry_run: bool,
+        /// Lista de mercaderias separada por comas, por defecto todas las de la linea
+        #[arg(long)]
+        ids: Option<String>,
+    },
+    /// Estado de la conexion y etiquetas aplicadas
+    Status,
+    /// Aplica las migraciones pendientes del esquema, contra el SQL Server de `[connection]`
+    Migrate {
+        /// Solo informa las migraciones pendientes
+        #[arg(long)]
+        dry_run: bool,
+        /// Registra como aplicadas las migraciones hasta esta version sin ejecutarlas, para
+        /// bases migradas a mano antes de `SchemaVersion`
+        #[arg(long, conflicts_with = "dry_run")]
+        baseline: Option<i32>,
+    },
+    /// Servidor HTTP con las mismas operaciones, para otros puestos de la planta
+    Serve {
+        /// Direccion en la que escuchar, por defecto la de la configuracion
+        #[arg(long)]
+        listen: Option<String>,
+        /// Carpeta con la version web (`trunk build`), para servirla desde el mismo origen
+        #[arg(long)]
+        web: Option<PathBuf>,
+    },
+}
+
+/// Resumen que muestra `status`.
+#[derive(Debug, Serialize)]
+struct Status {
+    backend: String,
+    host: String,
+    database: String,
+    medias: usize,
+    /// Cantidad de medias por etiqueta aplicada
+    aplicadas: BTreeMap<String, usize>,
+}
+
+/// Resultado de `migrate`.
+#[derive(Debug, Serialize)]
+struct Migraciones {
+    /// Version del esquema antes de migrar
+    anterior: i32,
+    /// Migraciones aplicadas, o pendientes con `--dry-run`
+    migraciones: Vec<String>,
+}
+
+/// Error en formato JSON.
+#[derive(Debug, Serialize)]
+struct Error {
+    /// Codigo de salida del proceso
+    exit: i32,
+    /// Codigo de error de la SP, si fallo ella
+    code: Option<u32>,
+    error: String,
+}
+
+#[async_std::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(constants::LOG_FILTER)),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    if let Err(err) = run(&cli).await {
+        let exit = exit_code(&err);
+        if cli.json {
+            print_json(&Error {
+                exit,
+                code: backend::error_code(&err),
+                error: format!("{err:#}"),
+            });
+        } else {
+            eprintln!("Error: {err:#}");
+        }
+        std::process::exit(exit)
+    }
+}
+
+async fn run(cli: &Cli) -> anyhow::Result<()> {
+    let config = config::load()?;
+    if let Comando::Serve { listen, web } = &cli.command {
+        let listen = listen.clone().unwrap_or_else(|| config.listen.clone());
+        return server::serve(config, &listen, web.as_deref()).await;
+    }
+    if let Comando::Migrate { dry_run, baseline } = &cli.command {
+        return migrate(cli, &config, *dry_run, *baseline).await;
+    }
+
+    let mut sql = backend::connect(&config).await?;
+
+    match &cli.command {
+        Comando::List { linea, dpi } => {
+            let linea = match linea {
+                Some(linea) => linea.parse::<Linea>()?,
+                None => config.estacion.linea,
+            };
+            let resolucion = match dpi {
+                Some(dpi) => Resolucion::try_from(dpi.parse::<u16>()?)?,
+                None => config.resolucion,
+            };
+            let table = sql.query_table(linea, resolucion).await?;
+            for rechazada in &table.rechazadas {
+                eprintln!("Aviso: {rechazada}");
+            }
+
+            if cli.json {
+                print_json(&table.filas);
+            } else {
+                for eti in table.filas {
+                    println!(
+                        "{:>3}  {:<2}  {:<24}  {:<16}  {}",
+                        eti.id,
+                        if eti.enable { "si" } else { "no" },
+                        eti.etiqueta,
+                        eti.label,
+                        eti.color
+                    );
+                }
+            }
+        }
+        Comando::Medias { linea } => {
+            let linea = match linea {
+                Some(linea) => linea.parse::<Linea>()?,
+                None => config.estacion.linea,
+            };
+            let ids = sql.query_ids(linea).await?;
+
+            if cli.json {
+                let ids: Vec<i32> = ids
+                    .split(',')
+                    .filter_map(|id| id.trim().parse().ok())
+                    .collect();
+                print_json(&ids);
+            } else {
+                println!("{ids}");
+            }
+        }
+        Comando::Set {
+            etiqueta,
+            dry_run,
+            ids,
+        } => {
+            let ids = match ids {
+                Some(ids) => ids.clone(),
+                None => sql.query_ids(config.estacion.linea).await?,
+            };
+
+            if *dry_run {
+                let cambios = sql.query_cambiar_etiquetas(&ids, etiqueta).await?;
+                if cli.json {
+                    print_json(&cambios);
+                } else {
+                    for cambio in &cambios {
+                        println!(
+                            "{:>6}  {} -> {}",
+                            cambio.mercaderia_id, cambio.actual, cambio.nueva
+                        );
+                    }
+                    println!("{} mercaderias cambiarian de etiqueta", cambios.len());
+                }
+            } else {
+                let cambio = sql
+                    .execute_cambiar_etiquetas(&ids, etiqueta, &Origen::local())
+                    .await?;
+                if cli.json {
+                    print_json(&cambio);
+                } else if cambio.sin_confirmar {
+                    println!("etiqueta {etiqueta} aplicada, no se pudo leer cuantas mercaderias cambiaron");
+                } else {
+                    println!("{} mercaderias cambiadas a {etiqueta}", cambio.rows);
+                    for eti in cambio.sin_cambiar() {
+                        println!("{:>6}  mantuvo {}", eti.mercaderia_id, eti.nombre());
+                    }
+                }
+            }
+        }
+        Comando::Serve { .. } | Comando::Migrate { .. } => {
+            unreachable!("Atendido antes de conectar.")
+        }
+        Comando::Status => {
+            let status = status(&config, sql.as_mut()).await?;
+
+            if cli.json {
+                print_json(&status);
+            } else {
+                println!(
+                    "conectado a {} ({}/{})",
+                    status.backend, status.host, status.database
+                );
+                println!("{} medias", status.medias);
+                for (etiqueta, cantidad) in &status.aplicadas {
+                    println!("{cantidad:>6}  {etiqueta}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn status(config: &Config, sql: &mut dyn Backend) -> anyhow::Result<Status> {
+    let ids = sql.query_ids(config.estacion.linea).await?;
+    let etiquetas: Vec<EtiquetaCaja> = sql.query_etiquetas(&ids).await?;
+
+    let mut aplicadas = BTreeMap::new();
+    for eti in &etiquetas {
+        *aplicadas.entry(eti.nombre().to_string()).or_default() += 1;
+    }
+
+    Ok(Status {
+        backend: match config.backend {
+            BackendKind::Sql => "sql",
+            BackendKind::Memory => "memory",
+            BackendKind::Http => "http",
+        }
+        .to_string(),
+        host: config.connection.host.clone(),
+        database: config.connection.database.clone(),
+        medias: ids.split(',').filter(|id| !id.trim().is_empty()).count(),
+        aplicadas,
+    })
+}
+
+/// Las migraciones van directo contra SQL Server, sin importar el backend configurado.
+async fn migrate(
+    cli: &Cli,
+    config: &Config,
+    dry_run: bool,
+    baseline: Option<i32>,
+) -> anyhow::Result<()> {
+    let mut sql = SQL::new_connection(&config.connection).await?;
+    let anterior = sql.schema_version().await?;
+
+    let migraciones = if dry_run {
+        sql.migraciones_pendientes().await?.to_vec()
+    } else if let Some(version) = baseline {
+        sql.marcar_migraciones(version).await?
+    } else {
+        sql.migrar().await?
+    };
+    let nombre = |migracion: &Migracion| format!("{:03}_{}", migracion.version, migracion.nombre);
+
+    if cli.json {
+        print_json(&Migraciones {
+            anterior,
+            migraciones: migraciones.iter().map(nombre).collect(),
+        });
+    } else if migraciones.is_empty() {
+        println!("el esquema esta al dia (version {anterior})");
+    } else {
+        let accion = match (dry_run, baseline) {
+            (true, _) => "pendiente",
+            (false, Some(_)) => "registrada",
+            (false, None) => "aplicada",
+        };
+        for migracion in &migraciones {
+            println!("{accion}  {}", nombre(migracion));
+        }
+    }
+
+    Ok(())
+}
+
+/// Codigo de salida: el del error de la SP, sino uno generico.
+fn exit_code(err: &anyhow::Error) -> i32 {
+    match backend::error_code(err) {
+        #[cfg(windows)]
+        Some(code) => code as i32,
+        #[cfg(not(windows))]
+        Some(code) => exit_code_procedure(code),
+        None if backend::is_connection_error(err) => constants::EXIT_CONNECTION,
+        None => constants::EXIT_ERROR,
+    }
+}
+
+/// Codigo de salida propio de cada error de la SP, fuera de Windows se truncarian a 8 bits.
+#[cfg(not(windows))]
+fn exit_code_procedure(code: u32) -> i32 {
+    match code {
+        constants::WARN_CODE => constants::EXIT_WARN,
+        constants::MISSING_CODE => constants::EXIT_MISSING,
+        constants::UPDATE_ERROR_CODE => constants::EXIT_UPDATE_ERROR,
+        constants::LINEA_CODE => constants::EXIT_LINEA,
+        constants::RAISERROR_CODE | constants::CONVERSION_CODE => constants::EXIT_PARAMETROS,
+        _ => constants::EXIT_PROCEDURE,
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("{err}"),
+    }
+}
@@ -1,14 +1,282 @@
+use crate::{
+    constants,
+    i18n::Idioma,
+    sql::{Linea, Resolucion},
+};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
 /// [yama's] Config
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
-    /// Indica si las etiquetas son de 300dpi o no.
-    pub is_dpi300: bool,
+    /// Idioma de la interfaz (`es`, `pt` o `en`).
+    pub idioma: Idioma,
+    /// Puesto de trabajo: linea de produccion y mercaderias que maneja.
+    pub estacion: Estacion,
+    /// Resolucion de las etiquetas a mostrar (`203`, `300` o `600`).
+    pub resolucion: Resolucion,
+    /// Formato anterior de la resolucion, solo se lee para migrar a `resolucion`.
+    #[serde(skip_serializing)]
+    pub is_dpi300: Option<bool>,
+    /// Base de datos contra la que corre la aplicación.
+    pub backend: BackendKind,
+    /// Parametros de conexion con el SQL Server.
+    pub connection: Connection,
+    /// Cantidad de cambios a mostrar en el historial.
+    pub history_len: usize,
+    /// Medias a las que se aplican las etiquetas, vacio aplica a todas.
+    pub selection: Vec<i32>,
+    /// Intervalo de recarga de la tabla de etiquetas, en segundos. 0 la desactiva.
+    pub refresh_interval: u64,
+    /// Rol de la base de datos que habilita la pantalla de administracion.
+    pub admin_role: String,
+    /// Direccion en la que escucha el servidor HTTP (`faena_cli serve`).
+    pub listen: String,
+    /// IPs de los puestos que pueden usar el servidor HTTP, ademas del propio equipo.
+    pub allowed_clients: Vec<IpAddr>,
+    /// URL del servidor HTTP para el backend `http`, vacia usa el mismo origen de la pagina.
+    pub server_url: String,
+    /// Distribucion de la grilla de botones.
+    pub grid: Grid,
+    /// Cambios de etiqueta que se aplican solos a una hora fija.
+    pub programacion: Vec<Programado>,
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Self { is_dpi300: true }
+        Self {
+            idioma: Idioma::Es,
+            estacion: Estacion::default(),
+            resolucion: Resolucion::Dpi300,
+            is_dpi300: None,
+            // En el navegador no hay conexion directa con SQL Server.
+            backend: if cfg!(target_arch = "wasm32") {
+                BackendKind::Http
+            } else {
+                BackendKind::Sql
+            },
+            connection: Connection::default(),
+            history_len: 20,
+            selection: Vec::new(),
+            refresh_interval: 30,
+            admin_role: "EtiquetasAdmin".to_string(),
+            listen: "127.0.0.1:8080".to_string(),
+            allowed_clients: Vec::new(),
+            server_url: if cfg!(target_arch = "wasm32") {
+                String::new()
+            } else {
+                "http://localhost:8080".to_string()
+            },
+            grid: Grid::default(),
+            programacion: Vec::new(),
+        }
     }
 }
+
+impl Config {
+    /// Convierte los campos de versiones anteriores, devuelve si habia alguno para guardar el
+    /// resultado.
+    fn migrar(&mut self) -> bool {
+        let Some(is_dpi300) = self.is_dpi300.take() else {
+            return false;
+        };
+
+        self.resolucion = if is_dpi300 {
+            Resolucion::Dpi300
+        } else {
+            Resolucion::Dpi203
+        };
+        true
+    }
+}
+
+/// Identidad del puesto, define que etiquetas y que mercaderias lista.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Estacion {
+    /// Linea de produccion (`faena`, `cuarteo` o `despostada`), filtra la tabla intermedia. El
+    /// tipo de mercaderia de cada linea esta en la tabla `Lineas`.
+    pub linea: Linea,
+}
+
+/// Distribucion de la grilla de botones, por defecto se adapta al tamaño de la pantalla.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    /// Cantidad de columnas, 0 la calcula segun el espacio y la cantidad de botones.
+    pub columns: usize,
+    /// Ancho fijo de los botones, 0 ocupa todo el espacio disponible.
+    pub button_width: f32,
+    /// Alto fijo de los botones, 0 ocupa todo el espacio disponible.
+    pub button_height: f32,
+    /// Separacion entre botones.
+    pub spacing: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            columns: 0,
+            button_width: 0.0,
+            button_height: 0.0,
+            spacing: constants::GRID_SPACE,
+        }
+    }
+}
+
+/// Cambio de etiqueta programado, por ejemplo el cambio de producto al inicio de cada turno.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Programado {
+    /// Hora local en formato `HH:MM`.
+    pub hora: String,
+    /// Dias en que se aplica (`Monday`, `Tuesday`, ...), vacio aplica todos los dias.
+    #[serde(default)]
+    pub dias: Vec<time::Weekday>,
+    /// Etiqueta a aplicar.
+    pub etiqueta: String,
+}
+
+/// Implementaciones disponibles de [`crate::backend::Backend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// SQL Server definido en [`Connection`].
+    Sql,
+    /// Base de datos en memoria, para desarrollo sin SQL Server.
+    Memory,
+    /// Servidor HTTP (`faena_cli serve`) definido en `server_url`, lo usa la version web.
+    Http,
+}
+
+/// Carga la configuracion, si no existe la genera con los valores por defecto.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> anyhow::Result<Config> {
+    let mut config: Config = confy::load("faena_etiquetas", "config")?;
+    if config.migrar() {
+        store(&config)?;
+    }
+    Ok(config)
+}
+
+/// Guarda la configuracion.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store(config: &Config) -> anyhow::Result<()> {
+    Ok(confy::store("faena_etiquetas", "config", config)?)
+}
+
+/// En el navegador la configuracion se guarda en el `localStorage`.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> anyhow::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| anyhow::anyhow!("localStorage no disponible."))
+}
+
+/// Carga la configuracion, si no existe usa los valores por defecto.
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> anyhow::Result<Config> {
+    match local_storage()?.get_item(STORAGE_KEY).ok().flatten() {
+        Some(json) => {
+            let mut config: Config = serde_json::from_str(&json)?;
+            if config.migrar() {
+                store(&config)?;
+            }
+            Ok(config)
+        }
+        None => Ok(Config::default()),
+    }
+}
+
+/// Guarda la configuracion.
+#[cfg(target_arch = "wasm32")]
+pub fn store(config: &Config) -> anyhow::Result<()> {
+    local_storage()?
+        .set_item(STORAGE_KEY, &serde_json::to_string(config)?)
+        .map_err(|err| anyhow::anyhow!("{err:?}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "faena_etiquetas";
+
+/// Parametros de conexion con el SQL Server, permite apuntar a prueba o producción sin recompilar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Connection {
+    /// Nombre o IP del servidor.
+    pub host: String,
+    /// Nombre de la instancia, se resuelve a traves del SQL Browser.
+    pub instance: Option<String>,
+    /// Puerto TCP, si no se especifica se usa el 1433 o el que indique el SQL Browser.
+    pub port: Option<u16>,
+    /// Base de datos, limita el alcance de la conexion en el servidor.
+    pub database: String,
+    /// Metodo de autenticacion.
+    pub auth: AuthMode,
+    /// Nivel de cifrado de la conexion.
+    pub tls: TlsMode,
+    /// Tiempo maximo para establecer la conexion, en segundos.
+    pub connect_timeout: u64,
+    /// Tiempo maximo de espera de cada consulta, en segundos.
+    pub query_timeout: u64,
+    /// Espera antes del primer reintento de conexion, en segundos. Se duplica en cada intento.
+    pub reconnect_initial: u64,
+    /// Espera maxima entre reintentos de conexion, en segundos.
+    pub reconnect_max: u64,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self {
+            host: "rafatest".to_string(),
+            instance: None,
+            port: None,
+            database: "AuxiliarFaena".to_string(),
+            auth: AuthMode::Integrated,
+            tls: TlsMode::TrustCert,
+            connect_timeout: 10,
+            query_timeout: 30,
+            reconnect_initial: 1,
+            reconnect_max: 60,
+        }
+    }
+}
+
+/// Metodos de autenticacion soportados.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AuthMode {
+    /// Autenticacion de Windows con el usuario de la sesion.
+    Integrated,
+    /// Login de SQL Server.
+    SqlLogin { user: String, password: String },
+}
+
+/// Niveles de cifrado de la conexion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    /// Cifra todo y valida el certificado del servidor.
+    Strict,
+    /// Cifra todo pero confia en cualquier certificado (certificados autofirmados).
+    TrustCert,
+    /// Solo cifra el login.
+    LoginOnly,
+}
+
+#[test]
+fn test_migrar_is_dpi300() {
+    let mut config: Config = serde_json::from_str(r#"{"is_dpi300": false}"#).unwrap();
+    assert!(config.migrar());
+    assert_eq!(config.resolucion, Resolucion::Dpi203);
+
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["resolucion"], 203);
+    assert!(json.get("is_dpi300").is_none());
+
+    // Una configuracion nueva no necesita migrar, y las resoluciones invalidas se rechazan.
+    let mut config: Config = serde_json::from_str(r#"{"resolucion": 600}"#).unwrap();
+    assert!(!config.migrar());
+    assert_eq!(config.resolucion, Resolucion::Dpi600);
+    assert!(serde_json::from_str::<Config>(r#"{"resolucion": 400}"#).is_err());
+}
@@ -2,20 +2,97 @@
 pub const MIN_ETI: u8 = 2;
 pub const MAX_ETI: u8 = 6;
 
+// Limites de las columnas de FaenaEtiquetas
+pub const ETIQUETA_LEN: usize = 200;
+pub const LABEL_LEN: usize = 16;
+pub const COLOR_LEN: usize = 7;
+pub const HOTKEY_LEN: usize = 16;
+
+// Contraste del texto de los botones, relacion de WCAG
+/// Con texto blanco o negro el nivel AA (4.5:1) se cumple con cualquier fondo, se pide el AAA
+/// para que los botones se lean de lejos.
+pub const MIN_CONTRAST: f32 = 7.0;
+
 // Log
+/// Filtro del log si no se define `RUST_LOG`: los eventos del programa, como los cambios
+/// programados, y solo los errores de las dependencias.
+pub const LOG_FILTER: &str = "error,faena_etiquetas=info";
 pub const LOG_FILENAME: &str = "faena_etiquetas.log";
 pub const LOG_FOLDER: &str = "/faena_etiquetas/logs";
 
 // Sizes
-pub const BUTTON_FONT_SIZE: f32 = 64.0;
-pub const BUTTON_HEIGHT: f32 = 212.0;
-pub const BUTTON_WIDTH: f32 = 381.0;
+/// Proporcion ancho/alto preferida de los botones, para elegir la cantidad de columnas.
+pub const BUTTON_ASPECT: f32 = 16.0 / 9.0;
+/// Tamaño de la letra de los botones respecto de su alto.
+pub const BUTTON_FONT_RATIO: f32 = 0.3;
+pub const MAX_BUTTON_FONT_SIZE: f32 = 128.0;
+pub const MIN_BUTTON_WIDTH: f32 = 160.0;
+pub const MIN_BUTTON_HEIGHT: f32 = 90.0;
 pub const GRID_SPACE: f32 = 32.0;
+pub const APPLIED_STROKE: f32 = 6.0;
 pub const ICON_SIZE: f32 = 64.0;
 pub const MODAL_WIDTH: f32 = 512.0;
 pub const MODAL_HEIGHT: f32 = 126.0;
+pub const PREVIEW_FONT_SIZE: f32 = 20.0;
+pub const HINT_FONT_SIZE: f32 = 24.0;
+pub const PREVIEW_HEIGHT: f32 = 320.0;
+pub const HISTORY_WIDTH: f32 = 560.0;
+pub const FLASH_STROKE: f32 = 8.0;
+pub const ADMIN_LABEL_WIDTH: f32 = 160.0;
+pub const ADMIN_COLOR_WIDTH: f32 = 80.0;
+pub const ADMIN_HOTKEY_WIDTH: f32 = 80.0;
+
+// Teclas por defecto de los botones, segun su posicion en la grilla
+pub const POSITION_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+// Animaciones
+/// Duracion del destello de la grilla cuando cambian los botones, en segundos.
+pub const FLASH_DURATION: f32 = 1.5;
+
+// Programacion
+/// Atraso maximo, en segundos, con el que se aplica un cambio programado; pasado ese tiempo se
+/// saltea hasta su proxima ocurrencia.
+pub const PROGRAMACION_GRACIA: i64 = 5 * 60;
+
+// Servidor HTTP
+/// Cambios que recuerda el servidor para poder deshacerlos.
+pub const DESHACER_LEN: usize = 32;
+
+// Codigos de salida de faena_cli. En Windows los errores de la SP salen con su propio codigo,
+// en el resto se truncarian a 8 bits y cada uno tiene uno de estos.
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_CONNECTION: i32 = 3;
+/// 57450, etiqueta no habilitada.
+pub const EXIT_WARN: i32 = 4;
+/// 58450, mercaderias inexistentes o inactivas.
+pub const EXIT_MISSING: i32 = 5;
+/// 56450, no se actualizo ninguna mercaderia.
+pub const EXIT_UPDATE_ERROR: i32 = 6;
+/// 50000 y 245, parametros vacios o invalidos.
+pub const EXIT_PARAMETROS: i32 = 7;
+/// Cualquier otro error lanzado por la SP.
+pub const EXIT_PROCEDURE: i32 = 8;
+/// 59450, etiqueta de otra linea.
+pub const EXIT_LINEA: i32 = 9;
 
 // Errors
-pub const ERROR_MSG: &str = "Hubo un problema! Comuniquese con Sistemas para solucionarlo";
-pub const WARN_MSG: &str = "Esta etiqueta no esta habilitada para producción";
+pub const ADMIN_HTTP_MSG: &str =
+    "La administracion de etiquetas no esta disponible a traves del servidor.";
 pub const WARN_CODE: u32 = 57450;
+pub const UPDATE_ERROR_CODE: u32 = 56450;
+pub const MISSING_CODE: u32 = 58450;
+pub const LINEA_CODE: u32 = 59450;
+/// Codigo que SQL Server asigna a los `RAISERROR` con mensaje.
+pub const RAISERROR_CODE: u32 = 50000;
+/// Codigo de SQL Server para un fallo de conversion de tipos.
+pub const CONVERSION_CODE: u32 = 245;
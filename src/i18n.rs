@@ -0,0 +1,192 @@
+//! Textos de la interfaz en cada idioma, para los puestos de plantas que no hablan castellano.
+//!
+//! Los catalogos son los archivos de `locales`, embebidos en el programa: un objeto JSON de
+//! clave a texto por idioma, con los parametros entre llaves (`{total}`). La ventana de
+//! administracion y los logs quedan en castellano, los usa Sistemas.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        OnceLock,
+    },
+};
+use tracing::error;
+
+/// Idiomas de la interfaz.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Idioma {
+    #[default]
+    Es,
+    Pt,
+    En,
+}
+
+impl Idioma {
+    pub const ALL: [Idioma; 3] = [Idioma::Es, Idioma::Pt, Idioma::En];
+
+    /// Nombre del idioma en si mismo, para el selector.
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Idioma::Es => "Español",
+            Idioma::Pt => "Português",
+            Idioma::En => "English",
+        }
+    }
+
+    /// Catalogo embebido.
+    fn fuente(self) -> &'static str {
+        match self {
+            Idioma::Es => include_str!("../locales/es.json"),
+            Idioma::Pt => include_str!("../locales/pt.json"),
+            Idioma::En => include_str!("../locales/en.json"),
+        }
+    }
+}
+
+/// Idioma actual, indice en `Idioma::ALL`.
+static IDIOMA: AtomicU8 = AtomicU8::new(0);
+
+/// Catalogos leidos la primera vez que se piden, en el orden de `Idioma::ALL`.
+static CATALOGOS: OnceLock<Vec<HashMap<String, String>>> = OnceLock::new();
+
+/// Cambia el idioma de la interfaz, se aplica desde el proximo cuadro.
+pub fn set_idioma(idioma: Idioma) {
+    IDIOMA.store(idioma as u8, Ordering::Relaxed);
+}
+
+/// Idioma actual de la interfaz.
+pub fn idioma() -> Idioma {
+    Idioma::ALL[IDIOMA.load(Ordering::Relaxed) as usize]
+}
+
+/// Lee un catalogo, un catalogo invalido queda vacio y sus textos salen en castellano.
+fn catalogo(idioma: Idioma) -> HashMap<String, String> {
+    serde_json::from_str(idioma.fuente()).unwrap_or_else(|err| {
+        error!("On i18n::catalogo {idioma:?}: {err}");
+        HashMap::new()
+    })
+}
+
+/// Texto de `clave` en el idioma actual. Si falta usa el castellano, y si tampoco esta la
+/// misma clave, asi se nota en pantalla.
+pub fn t(clave: &'static str) -> &'static str {
+    let catalogos = CATALOGOS.get_or_init(|| Idioma::ALL.into_iter().map(catalogo).collect());
+    catalogos[idioma() as usize]
+        .get(clave)
+        .or_else(|| catalogos[Idioma::Es as usize].get(clave))
+        .map_or(clave, String::as_str)
+}
+
+/// Texto de `clave` con sus parametros reemplazados.
+pub fn tf(clave: &'static str, parametros: &[(&str, &dyn std::fmt::Display)]) -> String {
+    parametros
+        .iter()
+        .fold(t(clave).to_string(), |texto, (nombre, valor)| {
+            texto.replace(&format!("{{{nombre}}}"), &valor.to_string())
+        })
+}
+
+#[test]
+fn test_catalogos() {
+    use std::collections::BTreeSet;
+
+    // Parametros de un texto, para verificar que las traducciones usen los mismos.
+    fn parametros(texto: &str) -> BTreeSet<&str> {
+        texto
+            .split('{')
+            .skip(1)
+            .filter_map(|resto| resto.split_once('}').map(|(nombre, _)| nombre))
+            .collect()
+    }
+
+    let es = catalogo(Idioma::Es);
+    assert!(!es.is_empty(), "El catalogo es.json no se pudo leer");
+
+    for idioma in Idioma::ALL {
+        let traduccion: HashMap<String, String> =
+            serde_json::from_str(idioma.fuente()).expect("Catalogo invalido");
+
+        for (clave, texto) in &es {
+            let Some(traducido) = traduccion.get(clave) else {
+                panic!("Falta la clave '{clave}' en {idioma:?}");
+            };
+            assert!(
+                !traducido.trim().is_empty(),
+                "'{clave}' vacia en {idioma:?}"
+            );
+            assert_eq!(
+                parametros(texto),
+                parametros(traducido),
+                "Parametros de '{clave}' en {idioma:?}"
+            );
+        }
+        for clave in traduccion.keys() {
+            assert!(
+                es.contains_key(clave),
+                "La clave '{clave}' de {idioma:?} no esta en es"
+            );
+        }
+    }
+
+    assert_eq!(
+        tf(
+            "medias.seleccionadas",
+            &[("seleccionadas", &3), ("total", &10)]
+        ),
+        "3 de 10 medias"
+    );
+    assert_eq!(t("clave.inexistente"), "clave.inexistente");
+}
+
+#[test]
+fn test_claves_usadas() {
+    use std::{fs, path::Path};
+
+    // Recorre el codigo buscando las claves literales de `i18n::t` y `i18n::tf`.
+    fn claves(dir: &Path, encontradas: &mut Vec<(String, String)>) {
+        for entrada in fs::read_dir(dir).expect("No se pudo leer src") {
+            let path = entrada.unwrap().path();
+            if path.is_dir() {
+                claves(&path, encontradas);
+                continue;
+            }
+            let codigo = fs::read_to_string(&path).unwrap();
+            for llamada in ["i18n::t(", "i18n::tf("] {
+                for (pos, _) in codigo.match_indices(llamada) {
+                    let resto = codigo[pos + llamada.len()..].trim_start();
+                    if let Some(clave) = resto
+                        .strip_prefix('"')
+                        .and_then(|resto| resto.split_once('"'))
+                        .map(|(clave, _)| clave)
+                        .filter(|clave| {
+                            !clave.is_empty()
+                                && clave
+                                    .chars()
+                                    .all(|c| c.is_ascii_lowercase() || c == '.' || c == '_')
+                        })
+                    {
+                        encontradas.push((path.display().to_string(), clave.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut encontradas = Vec::new();
+    claves(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut encontradas,
+    );
+    assert!(!encontradas.is_empty());
+
+    let es = catalogo(Idioma::Es);
+    for (archivo, clave) in encontradas {
+        assert!(
+            es.contains_key(&clave),
+            "La clave '{clave}' de {archivo} no esta en es"
+        );
+    }
+}
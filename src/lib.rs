@@ -1,8 +1,49 @@
 mod app;
 pub use app::App;
 
+pub mod backend;
+
 mod sql;
-pub use sql::SQL;
+pub use sql::{
+    Cambio, CambioError, EtiquetaCaja, Etiquetas, FilaRechazada, Linea, MotivoRechazo,
+    RegistroCambio, Resolucion, TablaEtiquetas,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use sql::{Migracion, SQL};
 
 pub mod config;
 pub mod constants;
+pub mod i18n;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+mod worker;
+
+/// Ejecuta `f` y devuelve lo que escribio en el log, con el filtro por defecto de la aplicación.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub(crate) fn capturar_log(f: impl FnOnce()) -> String {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new(constants::LOG_FILTER))
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+
+    let log = buffer.0.lock().unwrap().clone();
+    String::from_utf8(log).unwrap()
+}
@@ -1,6 +1,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use faena_etiquetas::{constants, App};
+#[cfg(not(target_arch = "wasm32"))]
+use faena_etiquetas::constants;
+use faena_etiquetas::App;
 
 #[cfg(target_arch = "wasm32")]
 fn main() {
@@ -14,7 +16,10 @@ fn main() {
             .start(
                 "runfo_canvas",
                 web_options,
-                Box::new(|cc| Box::new(App::new(cc))),
+                Box::new(|cc| {
+                    cc.egui_ctx.set_visuals(egui::Visuals::dark());
+                    Ok(Box::new(App::new(cc)))
+                }),
             )
             .await
             .expect("failed to start eframe");
@@ -44,20 +49,22 @@ fn main() -> eframe::Result<()> {
                 ..egui::Style::default()
             };
             cc.egui_ctx.set_style(style);
-            Ok(Box::new(async_std::task::block_on(App::new(cc))))
+            Ok(Box::new(App::new(cc)))
         }),
     );
 
+    #[cfg(windows)]
     clean_logs();
     app_rc
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[inline(always)]
 fn setup_logger() {
-    std::env::set_var("RUST_LOG", "error");
-
     #[cfg(windows)]
     let log_path = std::env::var("APPDATA").expect("No APPDATA directory") + constants::LOG_FOLDER;
+    #[cfg(not(windows))]
+    let log_path = std::env::temp_dir().display().to_string() + constants::LOG_FOLDER;
     if let Err(err) = std::fs::create_dir_all(&log_path) {
         eprintln!("{err}")
     }
@@ -70,13 +77,20 @@ fn setup_logger() {
     );
 
     tracing_subscriber::fmt::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_env_filter(log_filter())
         .with_ansi(false)
         .with_timer(timer)
         .with_writer(file_appender)
         .init();
 }
 
+/// Filtro de `RUST_LOG`, o `LOG_FILTER` si no esta definida.
+#[cfg(not(target_arch = "wasm32"))]
+fn log_filter() -> tracing_subscriber::EnvFilter {
+    tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(constants::LOG_FILTER))
+}
+
 #[cfg(windows)]
 #[inline(always)]
 /// Limpia los logs vacios
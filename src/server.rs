@@ -0,0 +1,556 @@
+//! Servidor HTTP con la misma funcionalidad que la interfaz, para que varios puestos de la
+//! planta operen sin tener cada uno su propia conexion con SQL Server.
+//!
+//! Todos los pedidos pasan por una unica conexion, de a uno por vez. Solo se atiende al propio
+//! equipo y a los puestos de `allowed_clients`.
+
+use crate::{
+    backend::{
+        self, Backend, CambioRequest, DeshacerRequest, ErrorResponse, Origen, ProcedureError,
+    },
+    config::Config,
+    constants,
+    sql::{EtiquetaCaja, Linea, Resolucion},
+};
+use anyhow::Context;
+use async_std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+};
+use tide::{Body, Next, Request, Response, StatusCode};
+use tracing::{error, warn};
+
+/// Operacion sobre la base de datos, se ejecuta con la conexion bloqueada.
+type Operacion<'a, T> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+struct State {
+    config: Arc<Config>,
+    /// Unica conexion, `None` hasta el primer pedido o luego de perderla
+    sql: Arc<Mutex<Option<Box<dyn Backend>>>>,
+    /// Etiquetas anteriores de los ultimos cambios, para deshacerlos
+    deshacer: Arc<Mutex<Deshacer>>,
+}
+
+/// Ultimos cambios aplicados con las etiquetas que tenian las mercaderias. Deshacer solo vuelve
+/// a estas, nunca a etiquetas que envie el cliente, asi no se saltea las validaciones de la SP.
+#[derive(Debug, Default)]
+struct Deshacer {
+    siguiente: u64,
+    cambios: VecDeque<(u64, Vec<EtiquetaCaja>)>,
+}
+
+impl Deshacer {
+    /// Guarda las etiquetas anteriores de un cambio, devuelve el id para deshacerlo.
+    fn guardar(&mut self, anteriores: Vec<EtiquetaCaja>) -> u64 {
+        self.siguiente += 1;
+        if self.cambios.len() == constants::DESHACER_LEN {
+            self.cambios.pop_front();
+        }
+        self.cambios.push_back((self.siguiente, anteriores));
+        self.siguiente
+    }
+
+    fn get(&self, id: u64) -> Option<Vec<EtiquetaCaja>> {
+        self.cambios
+            .iter()
+            .find(|(cambio, _)| *cambio == id)
+            .map(|(_, anteriores)| anteriores.clone())
+    }
+
+    /// Un cambio se deshace una sola vez.
+    fn quitar(&mut self, id: u64) {
+        self.cambios.retain(|(cambio, _)| *cambio != id);
+    }
+}
+
+/// Parametros de `GET /etiquetas`.
+#[derive(Debug, Deserialize)]
+struct EtiquetasQuery {
+    /// `faena`, `cuarteo` o `despostada`, por defecto la linea de la configuracion
+    linea: Option<String>,
+    /// 203, 300 o 600, por defecto la resolucion de la configuracion
+    dpi: Option<u16>,
+}
+
+/// Parametros de `GET /medias`.
+#[derive(Debug, Deserialize)]
+struct LineaQuery {
+    /// `faena`, `cuarteo` o `despostada`, por defecto la linea de la configuracion
+    linea: Option<String>,
+}
+
+/// Parametros de `GET /medias/etiquetas`.
+#[derive(Debug, Deserialize)]
+struct MediasQuery {
+    /// Lista de mercaderias separada por comas
+    ids: String,
+}
+
+/// Parametros de `GET /historial`.
+#[derive(Debug, Deserialize)]
+struct HistorialQuery {
+    limit: Option<usize>,
+}
+
+impl State {
+    /// Ejecuta la operacion con la conexion, conectando si hace falta, y arma la respuesta.
+    async fn run<T, F>(&self, operacion: F) -> tide::Result
+    where
+        T: Serialize,
+        F: for<'a> FnOnce(&'a mut dyn Backend) -> Operacion<'a, T>,
+    {
+        let mut sql = self.sql.lock().await;
+        if sql.is_none() {
+            match backend::connect(&self.config).await {
+                Ok(backend) => *sql = Some(backend),
+                Err(err) => {
+                    error!("On sql::new_connection: {err}");
+                    return error_response(&err);
+                }
+            }
+        }
+
+        let backend = sql.as_mut().expect("Conexion establecida.");
+        match operacion(backend.as_mut()).await {
+            Ok(value) => Ok(Response::builder(StatusCode::Ok)
+                .body(Body::from_json(&value)?)
+                .build()),
+            Err(err) => {
+                error!("On server: {err}");
+                // Se reconecta en el proximo pedido.
+                if backend::is_connection_error(&err) {
+                    *sql = None;
+                }
+                error_response(&err)
+            }
+        }
+    }
+}
+
+/// Arma la app con sus rutas, separada de `serve` para poder probarla sin abrir un puerto.
+fn app(config: Config) -> tide::Server<State> {
+    let mut app = tide::with_state(State {
+        config: Arc::new(config),
+        sql: Arc::new(Mutex::new(None)),
+        deshacer: Arc::new(Mutex::new(Deshacer::default())),
+    });
+
+    app.with(autorizar);
+    app.at("/etiquetas").get(etiquetas);
+    app.at("/medias").get(medias);
+    app.at("/medias/etiquetas").get(etiquetas_aplicadas);
+    app.at("/cambios").post(cambios);
+    app.at("/cambios/deshacer").post(deshacer);
+    app.at("/historial").get(historial);
+    app
+}
+
+/// Atiende pedidos en `listen` hasta que se cierre el proceso.
+///
+/// Si se indica `web`, tambien sirve la version web compilada con `trunk build` en esa carpeta,
+/// asi el navegador usa el mismo origen para la pagina y para la API.
+pub async fn serve(config: Config, listen: &str, web: Option<&Path>) -> anyhow::Result<()> {
+    let mut app = app(config);
+    if let Some(web) = web {
+        app.at("/").serve_file(web.join("index.html"))?;
+        app.at("/*").serve_dir(web)?;
+    }
+
+    app.listen(listen.to_string()).await?;
+    Ok(())
+}
+
+/// Rechaza los pedidos de equipos que no estan en `allowed_clients`. Usa la IP de la conexion y
+/// no `Forwarded`, que la escribe el cliente.
+fn autorizar<'a>(
+    req: Request<State>,
+    next: Next<'a, State>,
+) -> Pin<Box<dyn Future<Output = tide::Result> + Send + 'a>> {
+    Box::pin(async move {
+        let permitido = cliente(&req)
+            .is_some_and(|ip| ip.is_loopback() || req.state().config.allowed_clients.contains(&ip));
+        if !permitido {
+            warn!(
+                cliente = req.peer_addr(),
+                "Pedido rechazado, el cliente no esta en allowed_clients"
+            );
+            return Ok(Response::new(StatusCode::Forbidden));
+        }
+
+        Ok(next.run(req).await)
+    })
+}
+
+/// IP del cliente conectado.
+fn cliente(req: &Request<State>) -> Option<IpAddr> {
+    let ip = req.peer_addr()?.parse::<SocketAddr>().ok()?.ip();
+    // Un cliente IPv4 en un socket IPv6 llega como `::ffff:a.b.c.d`.
+    Some(ip.to_canonical())
+}
+
+/// Origen del pedido para el registro de auditoria: el equipo y usuario que informa el cliente,
+/// junto con su IP, que es lo unico verificado.
+fn origen(req: &Request<State>) -> Origen {
+    let ip = cliente(req).map_or_else(String::new, |ip| ip.to_string());
+    let header = |nombre: &str| {
+        req.header(nombre)
+            .map(|valor| valor.last().as_str().trim().to_string())
+            .filter(|valor| !valor.is_empty())
+    };
+
+    let estacion = match header(backend::ESTACION_HEADER) {
+        Some(estacion) => format!("{estacion} ({ip})"),
+        None => ip,
+    };
+    Origen {
+        // Largo de las columnas de `CambiosEtiqueta`.
+        estacion: estacion.chars().take(64).collect(),
+        usuario: header(backend::USUARIO_HEADER)
+            .unwrap_or_default()
+            .chars()
+            .take(128)
+            .collect(),
+    }
+}
+
+/// `GET /etiquetas?linea=faena&dpi=300`: tabla intermedia de una linea, las filas validas y
+/// las invalidas con su motivo.
+async fn etiquetas(req: Request<State>) -> tide::Result {
+    let (linea, resolucion) = tabla_query(&req)?;
+
+    req.state()
+        .run(|sql| Box::pin(async move { sql.query_table(linea, resolucion).await }))
+        .await
+}
+
+/// Linea y resolucion pedidas, por defecto las de la configuracion.
+fn tabla_query(req: &Request<State>) -> tide::Result<(Linea, Resolucion)> {
+    let query: EtiquetasQuery = req.query()?;
+    let linea = linea_query(req, query.linea)?;
+    let resolucion = match query.dpi {
+        None => req.state().config.resolucion,
+        Some(dpi) => Resolucion::try_from(dpi)
+            .map_err(|err| tide::Error::from_str(StatusCode::BadRequest, err.to_string()))?,
+    };
+
+    Ok((linea, resolucion))
+}
+
+/// Linea pedida, por defecto la de la configuracion.
+fn linea_query(req: &Request<State>, linea: Option<String>) -> tide::Result<Linea> {
+    match linea {
+        None => Ok(req.state().config.estacion.linea),
+        Some(linea) => linea
+            .parse::<Linea>()
+            .map_err(|err| tide::Error::from_str(StatusCode::BadRequest, err.to_string())),
+    }
+}
+
+/// `GET /medias?linea=faena`: ids de las mercaderias de una linea.
+async fn medias(req: Request<State>) -> tide::Result {
+    let query: LineaQuery = req.query()?;
+    let linea = linea_query(&req, query.linea)?;
+
+    req.state()
+        .run(|sql| {
+            Box::pin(async move {
+                Ok(sql
+                    .query_ids(linea)
+                    .await?
+                    .split(',')
+                    .filter_map(|id| id.trim().parse().ok())
+                    .collect::<Vec<i32>>())
+            })
+        })
+        .await
+}
+
+/// `GET /medias/etiquetas?ids=11,12`: etiqueta aplicada a cada mercaderia.
+async fn etiquetas_aplicadas(req: Request<State>) -> tide::Result {
+    let MediasQuery { ids } = req.query()?;
+
+    req.state()
+        .run(|sql| Box::pin(async move { sql.query_etiquetas(&ids).await }))
+        .await
+}
+
+/// `POST /cambios`: aplica una etiqueta, o en modo prueba devuelve las mercaderias que cambiarian.
+async fn cambios(mut req: Request<State>) -> tide::Result {
+    let CambioRequest {
+        etiqueta,
+        ids,
+        dry_run,
+    } = req.body_json().await?;
+    let linea = req.state().config.estacion.linea;
+    let origen = origen(&req);
+    let deshacer = req.state().deshacer.clone();
+
+    if dry_run {
+        req.state()
+            .run(|sql| {
+                Box::pin(async move {
+                    let ids = match ids {
+                        Some(ids) => ids,
+                        None => sql.query_ids(linea).await?,
+                    };
+                    sql.query_cambiar_etiquetas(&ids, &etiqueta).await
+                })
+            })
+            .await
+    } else {
+        req.state()
+            .run(|sql| {
+                Box::pin(async move {
+                    let ids = match ids {
+                        Some(ids) => ids,
+                        None => sql.query_ids(linea).await?,
+                    };
+                    let mut cambio = sql
+                        .execute_cambiar_etiquetas(&ids, &etiqueta, &origen)
+                        .await?;
+                    cambio.deshacer =
+                        Some(deshacer.lock().await.guardar(cambio.anteriores.clone()));
+                    Ok(cambio)
+                })
+            })
+            .await
+    }
+}
+
+/// `POST /cambios/deshacer`: vuelve las mercaderias de un cambio a las etiquetas que tenian.
+async fn deshacer(mut req: Request<State>) -> tide::Result {
+    let DeshacerRequest { id } = req.body_json().await?;
+    let origen = origen(&req);
+
+    if req.state().deshacer.lock().await.get(id).is_none() {
+        return Ok(Response::builder(StatusCode::NotFound)
+            .body(Body::from_json(&ErrorResponse {
+                code: None,
+                error: format!(
+                    "El cambio {id} no se puede deshacer, ya se deshizo o es anterior a los \
+                    ultimos {}.",
+                    constants::DESHACER_LEN
+                ),
+            })?)
+            .build());
+    }
+
+    // Se vuelve a buscar con la conexion bloqueada, asi dos pedidos no deshacen el mismo cambio.
+    let deshacer = req.state().deshacer.clone();
+    req.state()
+        .run(|sql| {
+            Box::pin(async move {
+                let anteriores = deshacer
+                    .lock()
+                    .await
+                    .get(id)
+                    .with_context(|| format!("El cambio {id} ya se deshizo."))?;
+                let rows = sql.restore_etiquetas(&anteriores, &origen).await?;
+                deshacer.lock().await.quitar(id);
+                Ok(rows)
+            })
+        })
+        .await
+}
+
+/// `GET /historial?limit=20`: ultimos cambios de etiqueta.
+async fn historial(req: Request<State>) -> tide::Result {
+    let query: HistorialQuery = req.query()?;
+    let limit = query.limit.unwrap_or(req.state().config.history_len);
+
+    req.state().run(|sql| sql.query_historial(limit)).await
+}
+
+/// Respuesta de error: 422 si la SP rechazo el pedido, 503 sin conexion y 500 en otro caso.
+fn error_response(err: &anyhow::Error) -> tide::Result {
+    let code = backend::error_code(err);
+    let status = if code.is_some() {
+        StatusCode::UnprocessableEntity
+    } else if backend::is_connection_error(err) {
+        StatusCode::ServiceUnavailable
+    } else {
+        StatusCode::InternalServerError
+    };
+
+    Ok(Response::builder(status)
+        .body(Body::from_json(&ErrorResponse {
+            code,
+            // El codigo va aparte, el mensaje de la SP queda sin el prefijo.
+            error: err
+                .downcast_ref::<ProcedureError>()
+                .map_or_else(|| err.to_string(), |err| err.message.clone()),
+        })?)
+        .build())
+}
+
+#[async_std::test]
+async fn test_server_memory() {
+    use crate::sql::TablaEtiquetas;
+    use tide::http::{Method, Request, Url};
+
+    let app = app(Config {
+        backend: crate::config::BackendKind::Memory,
+        allowed_clients: vec!["10.0.0.5".parse().unwrap()],
+        ..Default::default()
+    });
+    let url = |path: &str| Url::parse("http://localhost").unwrap().join(path).unwrap();
+    let pedido_de = |cliente: Option<&str>, method: Method, path: &str| {
+        let mut req = Request::new(method, url(path));
+        req.set_peer_addr(cliente);
+        req
+    };
+    let pedido = |method: Method, path: &str| pedido_de(Some("127.0.0.1:50000"), method, path);
+
+    // Solo atiende al propio equipo y a los puestos permitidos.
+    for (cliente, status) in [
+        (Some("10.0.0.5:50000"), StatusCode::Ok),
+        (Some("[::ffff:10.0.0.5]:50000"), StatusCode::Ok),
+        (Some("10.0.0.7:50000"), StatusCode::Forbidden),
+        (None, StatusCode::Forbidden),
+    ] {
+        let res: tide::http::Response = app
+            .respond(pedido_de(cliente, Method::Get, "/medias"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{cliente:?}");
+    }
+
+    let mut res: tide::http::Response = app
+        .respond(pedido(Method::Get, "/etiquetas?dpi=300"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::Ok);
+    let table: TablaEtiquetas = res.body_json().await.unwrap();
+    assert_eq!((table.filas.len(), table.rechazadas.len()), (6, 0));
+
+    let mut res: tide::http::Response = app
+        .respond(pedido(Method::Get, "/etiquetas?linea=cuarteo"))
+        .await
+        .unwrap();
+    let table: TablaEtiquetas = res.body_json().await.unwrap();
+    assert!(table.filas.is_empty());
+    let res: tide::http::Response = app
+        .respond(pedido(Method::Get, "/etiquetas?linea=playa"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BadRequest);
+
+    let mut req = pedido(Method::Post, "/cambios");
+    req.insert_header(backend::ESTACION_HEADER, "PC-01");
+    req.insert_header(backend::USUARIO_HEADER, "operador");
+    req.set_body(
+        Body::from_json(&CambioRequest {
+            etiqueta: "faena_hilton_2copias".to_string(),
+            ids: Some("11,12".to_string()),
+            dry_run: false,
+        })
+        .unwrap(),
+    );
+    let mut res: tide::http::Response = app.respond(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::Ok);
+    let cambio: backend::CambioAplicado = res.body_json().await.unwrap();
+    let id = cambio.deshacer.unwrap();
+
+    let mut req = pedido(Method::Post, "/cambios");
+    req.set_body(
+        Body::from_json(&CambioRequest {
+            etiqueta: "faena_hilton_2copias".to_string(),
+            ids: Some("999".to_string()),
+            dry_run: true,
+        })
+        .unwrap(),
+    );
+    let mut res: tide::http::Response = app.respond(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UnprocessableEntity);
+    let err: ErrorResponse = res.body_json().await.unwrap();
+    assert_eq!(err.code, Some(crate::constants::MISSING_CODE));
+
+    // La misma conexion en memoria conserva el cambio aplicado.
+    let mut res: tide::http::Response = app
+        .respond(pedido(Method::Get, "/historial"))
+        .await
+        .unwrap();
+    let historial: Vec<serde_json::Value> = res.body_json().await.unwrap();
+    assert_eq!(historial.len(), 1);
+    // El cambio queda registrado a nombre del cliente, con su IP.
+    assert_eq!(historial[0]["estacion"], "PC-01 (127.0.0.1)");
+    assert_eq!(historial[0]["usuario"], "operador");
+
+    // Solo se deshace un cambio que registro el servidor, y una sola vez.
+    for (id, status) in [
+        (id, StatusCode::Ok),
+        (id, StatusCode::NotFound),
+        (id + 100, StatusCode::NotFound),
+    ] {
+        let mut req = pedido(Method::Post, "/cambios/deshacer");
+        req.set_body(Body::from_json(&DeshacerRequest { id }).unwrap());
+        let res: tide::http::Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), status, "{id}");
+    }
+}
+
+#[async_std::test]
+async fn test_http_backend() {
+    use tide::listener::Listener;
+
+    let mut listener = app(Config {
+        backend: crate::config::BackendKind::Memory,
+        ..Default::default()
+    })
+    .bind("127.0.0.1:0")
+    .await
+    .unwrap();
+    let url = listener.info()[0].connection().to_string();
+    async_std::task::spawn(async move { listener.accept().await });
+
+    let mut http = backend::Http::connect(&url).await.unwrap();
+    let table = http
+        .query_table(Linea::Faena, Resolucion::Dpi300)
+        .await
+        .unwrap();
+    assert_eq!((table.filas.len(), table.rechazadas.len()), (6, 0));
+    assert!(http.query_ids(Linea::Cuarteo).await.is_err());
+
+    let origen = Origen {
+        estacion: "PC-02".to_string(),
+        usuario: "operador".to_string(),
+    };
+    let cambio = http
+        .execute_cambiar_etiquetas("11,12", "faena_hilton_2copias", &origen)
+        .await
+        .unwrap();
+    assert_eq!(cambio.rows, 2);
+    assert_eq!(
+        http.restore_etiquetas(&cambio.anteriores, &origen)
+            .await
+            .unwrap(),
+        2
+    );
+
+    // Los errores de la SP conservan su codigo.
+    let err = http
+        .query_cambiar_etiquetas("999", "faena_hilton_2copias")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        backend::error_code(&err),
+        Some(crate::constants::MISSING_CODE)
+    );
+
+    let etiquetas = http.query_etiquetas("11,12").await.unwrap();
+    assert!(etiquetas
+        .iter()
+        .all(|eti| eti.nombre() == "faena_h_aa_2copias"));
+    let historial = http.query_historial(10).await.unwrap();
+    assert_eq!(historial.len(), 2);
+    assert!(historial
+        .iter()
+        .all(|registro| registro.estacion == "PC-02 (127.0.0.1)"));
+}
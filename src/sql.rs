@@ -1,105 +1,923 @@
-use anyhow::Context;
-use async_std::net::TcpStream;
-use tiberius::{Client, Config, ExecuteResult};
-use tiberius::{Query, SqlBrowser};
+use crate::{constants, i18n};
+use serde::{Deserialize, Serialize};
+use time::PrimitiveDateTime;
+
+mod error;
+pub use error::CambioError;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod migraciones;
+#[cfg(not(target_arch = "wasm32"))]
+pub use migraciones::Migracion;
+
+// La conexion con SQL Server no existe en la version web.
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    crate::config::{self, AuthMode, TlsMode},
+    anyhow::Context,
+    async_std::net::TcpStream,
+    std::{collections::BTreeMap, future::Future, time::Duration},
+    tiberius::{AuthMethod, Client, Config, EncryptionLevel, ExecuteResult},
+    tiberius::{Query, Row, SqlBrowser},
+    tracing::{error, warn},
+};
 
 /// Envoltura a la conexion con SQL Server
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub struct SQL {
     pub client: Client<TcpStream>,
+    /// Tiempo maximo de espera de cada consulta
+    query_timeout: Duration,
 }
 
 /// Estructura que define una fila de la tabla intermedia de la base de datos
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Etiquetas {
     pub id: u8,
     pub enable: bool,
     pub etiqueta: String,
     pub label: String,
     pub color: String,
-    pub dpi300: bool,
+    /// Resolucion de la impresora para la que es la etiqueta
+    pub dpi: Resolucion,
+    /// Linea de produccion en la que se muestra el boton
+    #[serde(default)]
+    pub linea: Linea,
+    /// Tecla que activa el boton (nombre de `egui::Key`), `None` usa su posicion en la grilla
+    #[serde(default)]
+    pub hotkey: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Etiquetas {
+    /// Convierte una fila de `FaenaEtiquetas`, falla si falta una columna o tiene un valor que
+    /// no corresponde.
+    fn try_from_row(row: &Row) -> Result<Self, MotivoRechazo> {
+        let dpi = columna::<i16>(row, "dpi")?;
+        let linea = columna::<&str>(row, "linea")?;
+        Ok(Etiquetas {
+            id: columna(row, "id")?,
+            enable: columna(row, "enable")?,
+            etiqueta: columna::<&str>(row, "etiqueta")?.to_string(),
+            label: columna::<&str>(row, "label")?.to_string(),
+            color: columna::<&str>(row, "color")?.to_string(),
+            dpi: Resolucion::try_from(dpi as u16).map_err(|_| MotivoRechazo::Valor {
+                columna: "dpi".to_string(),
+                valor: dpi.to_string(),
+            })?,
+            linea: linea.parse().map_err(|_| MotivoRechazo::Valor {
+                columna: "linea".to_string(),
+                valor: linea.to_string(),
+            })?,
+            // Columna opcional, sin ella los botones usan su posicion.
+            hotkey: row
+                .try_get::<&str, &str>("hotkey")
+                .ok()
+                .flatten()
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Valor no nulo de una columna.
+#[cfg(not(target_arch = "wasm32"))]
+fn columna<'a, T: tiberius::FromSql<'a>>(row: &'a Row, nombre: &str) -> Result<T, MotivoRechazo> {
+    row.try_get(nombre)
+        .map_err(|_| MotivoRechazo::ColumnaInvalida {
+            columna: nombre.to_string(),
+        })?
+        .ok_or_else(|| MotivoRechazo::ColumnaNula {
+            columna: nombre.to_string(),
+        })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FilaRechazada {
+    /// Fila que no se pudo convertir, con lo que se pueda leer para identificarla.
+    fn from_row(row: &Row, motivo: MotivoRechazo) -> Self {
+        FilaRechazada {
+            id: row.try_get("id").ok().flatten(),
+            etiqueta: row
+                .try_get::<&str, &str>("etiqueta")
+                .ok()
+                .flatten()
+                .map(str::to_string),
+            motivo,
+        }
+    }
+}
+
+impl Etiquetas {
+    /// Valida la fila contra los limites de las columnas de `FaenaEtiquetas`.
+    pub fn validar(&self) -> Result<(), MotivoRechazo> {
+        let etiqueta = self.etiqueta.chars().count();
+        if !(1..=constants::ETIQUETA_LEN).contains(&etiqueta) {
+            return Err(MotivoRechazo::EtiquetaLargo);
+        }
+
+        let label = self.label.trim().chars().count();
+        if !(1..=constants::LABEL_LEN).contains(&label) {
+            return Err(MotivoRechazo::LabelLargo);
+        }
+
+        if !(self.color.len() == constants::COLOR_LEN
+            && self.color.starts_with('#')
+            && self.color[1..].chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Err(MotivoRechazo::Color);
+        }
+
+        // Enter y Escape responden el aviso de confirmacion.
+        if let Some(hotkey) = &self.hotkey {
+            if !egui::Key::from_name(hotkey)
+                .is_some_and(|key| !matches!(key, egui::Key::Enter | egui::Key::Escape))
+            {
+                return Err(MotivoRechazo::Hotkey {
+                    tecla: hotkey.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Valida que ninguna otra etiqueta de la misma linea y resolucion use la tecla, replica el
+    /// indice unico `IX_Unique_FaenaEtiquetas_hotkey`.
+    pub fn validar_hotkey<'a>(
+        &self,
+        tabla: impl IntoIterator<Item = &'a Etiquetas>,
+    ) -> Result<(), MotivoRechazo> {
+        let Some(key) = self.hotkey_key() else {
+            return Ok(());
+        };
+        if let Some(otra) = tabla.into_iter().find(|eti| {
+            eti.id != self.id
+                && eti.linea == self.linea
+                && eti.dpi == self.dpi
+                && eti.hotkey_key() == Some(key)
+        }) {
+            return Err(MotivoRechazo::HotkeyRepetida {
+                tecla: key.name().to_string(),
+                etiqueta: otra.etiqueta.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Tecla configurada en la tabla, `None` si no tiene o no es valida.
+    pub fn hotkey_key(&self) -> Option<egui::Key> {
+        self.hotkey.as_deref().and_then(egui::Key::from_name)
+    }
+}
+
+/// Fila de `FaenaEtiquetas` que no se muestra por tener datos invalidos.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilaRechazada {
+    /// `None` si no se pudo leer
+    pub id: Option<u8>,
+    pub etiqueta: Option<String>,
+    /// Columna o validacion que fallo
+    pub motivo: MotivoRechazo,
+}
+
+impl FilaRechazada {
+    /// Fila convertida que no paso `Etiquetas::validar`.
+    fn invalida(etiqueta: &Etiquetas, motivo: MotivoRechazo) -> Self {
+        FilaRechazada {
+            id: Some(etiqueta.id),
+            etiqueta: Some(etiqueta.etiqueta.clone()),
+            motivo,
+        }
+    }
+}
+
+/// Por que una fila de `FaenaEtiquetas` no se puede usar. Se muestra en castellano en la
+/// administracion y en el log, y en el idioma de la interfaz en el diagnostico del operador.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum MotivoRechazo {
+    /// La columna no existe o tiene otro tipo de dato
+    ColumnaInvalida { columna: String },
+    /// La columna es nula
+    ColumnaNula { columna: String },
+    /// Resolucion o linea que el programa no conoce
+    Valor { columna: String, valor: String },
+    /// `etiqueta` vacia o mas larga que `ETIQUETA_LEN`
+    EtiquetaLargo,
+    /// `label` vacio o mas largo que `LABEL_LEN`
+    LabelLargo,
+    /// `color` sin el formato `#RRGGBB`
+    Color,
+    /// `hotkey` que no es una tecla o responde el aviso de confirmacion
+    Hotkey { tecla: String },
+    /// `hotkey` que ya usa otra etiqueta de la misma linea y resolucion
+    HotkeyRepetida { tecla: String, etiqueta: String },
+}
+
+impl MotivoRechazo {
+    /// Motivo para el operador, en el idioma de la interfaz.
+    pub fn mensaje(&self) -> String {
+        match self {
+            Self::ColumnaInvalida { columna } => {
+                i18n::tf("rechazo.columna_invalida", &[("columna", columna)])
+            }
+            Self::ColumnaNula { columna } => {
+                i18n::tf("rechazo.columna_nula", &[("columna", columna)])
+            }
+            Self::Valor { columna, valor } => {
+                i18n::tf("rechazo.valor", &[("columna", columna), ("valor", valor)])
+            }
+            Self::EtiquetaLargo => i18n::tf(
+                "rechazo.etiqueta_largo",
+                &[("largo", &constants::ETIQUETA_LEN)],
+            ),
+            Self::LabelLargo => {
+                i18n::tf("rechazo.label_largo", &[("largo", &constants::LABEL_LEN)])
+            }
+            Self::Color => i18n::t("rechazo.color").to_string(),
+            Self::Hotkey { tecla } => i18n::tf("rechazo.hotkey", &[("tecla", tecla)]),
+            Self::HotkeyRepetida { tecla, etiqueta } => i18n::tf(
+                "rechazo.hotkey_repetida",
+                &[("tecla", tecla), ("etiqueta", etiqueta)],
+            ),
+        }
+    }
 }
 
+impl std::fmt::Display for MotivoRechazo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ColumnaInvalida { columna } => write!(f, "Columna '{columna}' invalida."),
+            Self::ColumnaNula { columna } => {
+                write!(f, "Columna '{columna}' no encontrada o nula.")
+            }
+            Self::Valor { columna, valor } => {
+                write!(f, "Valor '{valor}' no soportado en la columna '{columna}'.")
+            }
+            Self::EtiquetaLargo => write!(
+                f,
+                "La etiqueta debe tener entre 1 y {} caracteres.",
+                constants::ETIQUETA_LEN
+            ),
+            Self::LabelLargo => write!(
+                f,
+                "El texto del boton debe tener entre 1 y {} caracteres.",
+                constants::LABEL_LEN
+            ),
+            Self::Color => f.write_str("El color debe tener el formato #RRGGBB."),
+            Self::Hotkey { tecla } => write!(f, "La tecla '{tecla}' no es valida."),
+            Self::HotkeyRepetida { tecla, etiqueta } => {
+                write!(f, "La tecla '{tecla}' ya la usa la etiqueta {etiqueta}.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MotivoRechazo {}
+
+impl std::fmt::Display for FilaRechazada {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.id {
+            Some(id) => write!(f, "fila {id}")?,
+            None => f.write_str("fila sin id")?,
+        }
+        if let Some(etiqueta) = &self.etiqueta {
+            write!(f, " ({etiqueta})")?;
+        }
+        write!(f, ": {}", self.motivo)
+    }
+}
+
+/// Tabla intermedia leida para la grilla: las filas validas y las que se descartaron.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TablaEtiquetas {
+    pub filas: Vec<Etiquetas>,
+    pub rechazadas: Vec<FilaRechazada>,
+}
+
+impl FromIterator<Result<Etiquetas, FilaRechazada>> for TablaEtiquetas {
+    /// Separa las filas convertidas que pasan `Etiquetas::validar` del resto. Si dos filas usan
+    /// la misma tecla se rechaza la segunda.
+    fn from_iter<I: IntoIterator<Item = Result<Etiquetas, FilaRechazada>>>(filas: I) -> Self {
+        let mut tabla = TablaEtiquetas::default();
+        for fila in filas {
+            match fila {
+                Ok(etiqueta) => match etiqueta
+                    .validar()
+                    .and_then(|()| etiqueta.validar_hotkey(&tabla.filas))
+                {
+                    Ok(()) => tabla.filas.push(etiqueta),
+                    Err(motivo) => tabla
+                        .rechazadas
+                        .push(FilaRechazada::invalida(&etiqueta, motivo)),
+                },
+                Err(rechazada) => tabla.rechazadas.push(rechazada),
+            }
+        }
+        tabla
+    }
+}
+
+/// Resoluciones de impresora soportadas, en la configuracion y en JSON se escriben en dpi.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Resolucion {
+    Dpi203,
+    #[default]
+    Dpi300,
+    Dpi600,
+}
+
+impl Resolucion {
+    pub const ALL: [Resolucion; 3] = [Resolucion::Dpi203, Resolucion::Dpi300, Resolucion::Dpi600];
+
+    /// Puntos por pulgada, valor de la columna `dpi`.
+    pub fn dpi(self) -> u16 {
+        match self {
+            Resolucion::Dpi203 => 203,
+            Resolucion::Dpi300 => 300,
+            Resolucion::Dpi600 => 600,
+        }
+    }
+}
+
+impl From<Resolucion> for u16 {
+    fn from(resolucion: Resolucion) -> Self {
+        resolucion.dpi()
+    }
+}
+
+impl TryFrom<u16> for Resolucion {
+    type Error = anyhow::Error;
+
+    fn try_from(dpi: u16) -> anyhow::Result<Self> {
+        Resolucion::ALL
+            .into_iter()
+            .find(|resolucion| resolucion.dpi() == dpi)
+            .ok_or_else(|| anyhow::anyhow!("Resolucion {dpi} no soportada."))
+    }
+}
+
+impl std::fmt::Display for Resolucion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} dpi", self.dpi())
+    }
+}
+
+/// Lineas de produccion en las que se instala la aplicación, cada una con sus etiquetas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Linea {
+    #[default]
+    Faena,
+    Cuarteo,
+    Despostada,
+}
+
+impl Linea {
+    pub const ALL: [Linea; 3] = [Linea::Faena, Linea::Cuarteo, Linea::Despostada];
+
+    /// Valor de la columna `linea`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Linea::Faena => "faena",
+            Linea::Cuarteo => "cuarteo",
+            Linea::Despostada => "despostada",
+        }
+    }
+}
+
+impl std::str::FromStr for Linea {
+    type Err = anyhow::Error;
+
+    fn from_str(linea: &str) -> anyhow::Result<Self> {
+        Linea::ALL
+            .into_iter()
+            .find(|valor| valor.as_str() == linea.trim())
+            .ok_or_else(|| anyhow::anyhow!("Linea '{linea}' no soportada."))
+    }
+}
+
+impl std::fmt::Display for Linea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Mercaderia que modificaria `CambiarEtiquetas`, obtenida en modo prueba
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cambio {
+    pub mercaderia_id: i32,
+    /// Etiqueta que tiene aplicada actualmente
+    pub actual: String,
+    /// Etiqueta que se le aplicaria
+    pub nueva: String,
+}
+
+/// Etiqueta de caja aplicada a una mercaderia (`MercaderiasEtiquetaCaja`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EtiquetaCaja {
+    pub mercaderia_id: i32,
+    /// `sEtiqueta`, `None` si es nulo
+    pub etiqueta: Option<String>,
+}
+
+impl EtiquetaCaja {
+    /// `sEtiqueta` para mostrar, vacio si es nulo.
+    pub fn nombre(&self) -> &str {
+        self.etiqueta.as_deref().unwrap_or_default()
+    }
+}
+
+/// Fila de la tabla de auditoria `CambiosEtiqueta`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistroCambio {
+    /// Al insertar se ignora, la fecha la asigna el servidor
+    pub fecha: PrimitiveDateTime,
+    /// Nombre del equipo que realizo el cambio
+    pub estacion: String,
+    /// Usuario de Windows que realizo el cambio
+    pub usuario: String,
+    /// Etiquetas previas separadas por comas
+    pub anterior: String,
+    pub nueva: String,
+    /// Cantidad de mercaderias a cambiar
+    pub cantidad: i32,
+    /// 0 si fue exitoso, sino el codigo de error
+    pub codigo: i32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl SQL {
     /// Conexion con el SQL Server de runfo
-    pub async fn new_connection() -> anyhow::Result<Self> {
+    pub async fn new_connection(connection: &config::Connection) -> anyhow::Result<Self> {
         let mut config = Config::new();
 
-        // Autenticacion de Windows
-        config.authentication(tiberius::AuthMethod::Integrated);
+        match &connection.auth {
+            // Autenticacion de Windows
+            #[cfg(windows)]
+            AuthMode::Integrated => config.authentication(AuthMethod::Integrated),
+            #[cfg(not(windows))]
+            AuthMode::Integrated => {
+                anyhow::bail!("La autenticacion de Windows solo esta disponible en Windows.")
+            }
+            AuthMode::SqlLogin { user, password } => {
+                config.authentication(AuthMethod::sql_server(user, password))
+            }
+        }
 
         // SQL Server IP
-        config.host("rafatest");
+        config.host(&connection.host);
+        if let Some(instance) = &connection.instance {
+            config.instance_name(instance);
+        }
+        if let Some(port) = connection.port {
+            config.port(port);
+        }
 
         // Especificamos la base de datos para limitar su alcance en el servidor
-        config.database("AuxiliarFaena");
+        config.database(&connection.database);
+
+        match connection.tls {
+            TlsMode::Strict => config.encryption(EncryptionLevel::Required),
+            TlsMode::TrustCert => {
+                config.encryption(EncryptionLevel::Required);
+                config.trust_cert();
+            }
+            TlsMode::LoginOnly => {
+                config.encryption(EncryptionLevel::Off);
+                config.trust_cert();
+            }
+        }
 
-        config.trust_cert();
+        let connect_timeout = Duration::from_secs(connection.connect_timeout);
+        let client = async_std::future::timeout(connect_timeout, async {
+            let tcp = TcpStream::connect_named(&config).await?;
+            Client::connect(config, tcp).await
+        })
+        .await
+        .with_context(|| format!("No se pudo conectar a '{}'.", connection.host))??;
 
-        let tcp = TcpStream::connect_named(&config).await?;
-        let client = Client::connect(config, tcp).await?;
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            query_timeout: Duration::from_secs(connection.query_timeout),
+        })
     }
 
-    /// Ejecuta el Stored Procedure para cambiar las etiquetas
+    /// Ejecuta el Stored Procedure para cambiar las etiquetas, devuelve la cantidad de
+    /// mercaderias actualizadas y las que mantuvieron su etiqueta, `None` si el cambio se
+    /// aplico pero no se pudo saber el resultado.
     pub async fn execute_cambiar_etiquetas(
         &mut self,
         ids: &str,
         etiqueta: &str,
-    ) -> tiberius::Result<ExecuteResult> {
-        self.client
-            .execute(
-                "EXECUTE [cambiarEtiquetas].[CambiarEtiquetas] @P1, @P2, @P3",
-                &[&ids, &etiqueta, &"1"],
-            )
-            .await
+    ) -> anyhow::Result<Option<(u64, Vec<i32>)>> {
+        let mut exec = Query::new("EXECUTE [cambiarEtiquetas].[CambiarEtiquetas] @P1, @P2, @P3");
+        exec.bind(ids);
+        exec.bind(etiqueta);
+        exec.bind(true);
+
+        let results = timeout(self.query_timeout, async {
+            exec.query(&mut self.client).await?.into_results().await
+        })
+        .await?;
+
+        // El cambio ya se aplico: si el resultado no llego completo no se puede fallar, se
+        // cuentan las mercaderias que quedaron con la etiqueta nueva.
+        let actualizadas = results
+            .first()
+            .and_then(|rows| rows.first())
+            .and_then(|row| row.try_get::<i32, &str>("actualizadas").ok().flatten());
+        let Some(actualizadas) = actualizadas else {
+            warn!("'CambiarEtiquetas' no devolvio la cantidad de mercaderias actualizadas, se recuentan.");
+            return Ok(self.recontar_cambio(ids, etiqueta).await);
+        };
+
+        let faltantes = results
+            .get(1)
+            .into_iter()
+            .flatten()
+            .filter_map(|row| row.try_get("Mercaderia_Id").ok().flatten())
+            .collect();
+
+        Ok(Some((actualizadas as u64, faltantes)))
+    }
+
+    /// Cuenta las mercaderias de la lista que tienen la etiqueta, las demas son las faltantes.
+    /// `None` si tampoco se pueden leer las etiquetas, no se inventa una cantidad.
+    async fn recontar_cambio(&mut self, ids: &str, etiqueta: &str) -> Option<(u64, Vec<i32>)> {
+        let actuales = match self.query_etiquetas(ids).await {
+            Ok(actuales) => actuales,
+            Err(err) => {
+                error!("On sql::recontar_cambio: {err}");
+                return None;
+            }
+        };
+
+        let actualizadas = actuales
+            .iter()
+            .filter(|eti| eti.etiqueta.as_deref() == Some(etiqueta))
+            .count();
+        let mut faltantes: Vec<i32> = ids
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .filter(|id| {
+                !actuales.iter().any(|eti| {
+                    eti.mercaderia_id == *id && eti.etiqueta.as_deref() == Some(etiqueta)
+                })
+            })
+            .collect();
+        faltantes.sort_unstable();
+        faltantes.dedup();
+
+        Some((actualizadas as u64, faltantes))
+    }
+
+    /// Ejecuta el Stored Procedure en modo prueba, devuelve las filas de `MercaderiasEtiquetaCaja`
+    /// que se modificarian.
+    pub async fn query_cambiar_etiquetas(
+        &mut self,
+        ids: &str,
+        etiqueta: &str,
+    ) -> anyhow::Result<Vec<Cambio>> {
+        let mut exec = Query::new("EXECUTE [cambiarEtiquetas].[CambiarEtiquetas] @P1, @P2, @P3");
+        exec.bind(ids);
+        exec.bind(etiqueta);
+        exec.bind(false);
+
+        let rows = timeout(self.query_timeout, async {
+            exec.query(&mut self.client).await?.into_results().await
+        })
+        .await?;
+
+        rows.first()
+            .context("El modo prueba de 'CambiarEtiquetas' no devolvio resultados.")?
+            .iter()
+            .map(|row| {
+                Ok(Cambio {
+                    mercaderia_id: row
+                        .try_get("Mercaderia_Id")?
+                        .context("Columna 'Mercaderia_Id' nula.")?,
+                    actual: row
+                        .try_get::<&str, &str>("sEtiqueta")?
+                        .unwrap_or_default()
+                        .to_string(),
+                    nueva: etiqueta.to_string(),
+                })
+            })
+            .collect()
     }
 
-    /// Obtiene la tabla intermedia de etiquetas
-    pub async fn query_table(&mut self, is_dpi300: bool) -> anyhow::Result<Vec<Etiquetas>> {
-        let mut select =
-            Query::new("SELECT * FROM [cambiarEtiquetas].[FaenaEtiquetas] WHERE [dpi300] = @P1");
-        select.bind(is_dpi300);
+    /// Obtiene la tabla intermedia de etiquetas de una linea
+    pub async fn query_table(
+        &mut self,
+        linea: Linea,
+        resolucion: Resolucion,
+    ) -> anyhow::Result<TablaEtiquetas> {
+        let mut select = Query::new(
+            "SELECT * FROM [cambiarEtiquetas].[FaenaEtiquetas] WHERE [linea] = @P1 AND [dpi] = @P2",
+        );
+        select.bind(linea.as_str());
+        select.bind(resolucion.dpi() as i16);
 
-        let stream = select.query(&mut self.client).await?;
-        let rows = stream.into_results().await?;
+        let rows = timeout(self.query_timeout, async {
+            select.query(&mut self.client).await?.into_results().await
+        })
+        .await?;
 
         Ok(rows
             .first()
             .context("La query a la tabla 'FaenaEtiquetas' esta vacia.")?
             .iter()
-            .map(|row| Etiquetas {
-                id: row.get("id").expect("Columna 'id' no encontrada."),
-                enable: row.get("enable").expect("Columna 'enable' no encontrada."),
-                etiqueta: row
-                    .get::<&str, &str>("etiqueta")
-                    .expect("Columna 'etiqueta' no encontrada.")
-                    .to_string(),
-                label: row
-                    .get::<&str, &str>("label")
-                    .expect("Columna 'label' no encontrada.")
-                    .to_string(),
-                color: row
-                    .get::<&str, &str>("color")
-                    .expect("Columna 'color' no encontrada.")
-                    .to_string(),
-                dpi300: row.get("dpi300").expect("Columna 'dpi300' no encontrada."),
+            .map(|row| {
+                Etiquetas::try_from_row(row).map_err(|motivo| FilaRechazada::from_row(row, motivo))
+            })
+            .collect())
+    }
+
+    /// Obtiene la tabla intermedia completa, de todas las lineas y resoluciones, para
+    /// administrarla.
+    pub async fn query_table_completa(&mut self) -> anyhow::Result<Vec<Etiquetas>> {
+        let select = Query::new("SELECT * FROM [cambiarEtiquetas].[FaenaEtiquetas] ORDER BY [id]");
+
+        let rows = timeout(self.query_timeout, async {
+            select
+                .query(&mut self.client)
+                .await?
+                .into_first_result()
+                .await
+        })
+        .await?;
+
+        // Las filas invalidas tambien se muestran para corregirlas, salvo las que no se pueden
+        // convertir.
+        Ok(rows
+            .iter()
+            .filter_map(|row| match Etiquetas::try_from_row(row) {
+                Ok(etiqueta) => Some(etiqueta),
+                Err(motivo) => {
+                    error!(
+                        "On sql::query_table_completa: {}",
+                        FilaRechazada::from_row(row, motivo)
+                    );
+                    None
+                }
             })
             .collect())
     }
 
-    /// Obtiene la lista de media reses.
-    pub async fn query_ids(&mut self) -> anyhow::Result<String> {
-        let select = Query::new(
-            "DECLARE @mercaderias varchar(max)
-            EXECUTE [cambiarEtiquetas].[ListarMedias] @mercaderias OUTPUT
-            SELECT @mercaderias",
+    /// Indica si el usuario de la conexion puede administrar la tabla intermedia, ya sea por
+    /// pertenecer al rol indicado o por ser dueño de la base.
+    pub async fn is_admin(&mut self, role: &str) -> anyhow::Result<bool> {
+        let mut select = Query::new(
+            "SELECT CAST(CASE WHEN IS_ROLEMEMBER(@P1) = 1 OR IS_MEMBER('db_owner') = 1
+                THEN 1 ELSE 0 END AS BIT)",
+        );
+        select.bind(role.to_string());
+
+        let row = timeout(self.query_timeout, async {
+            select.query(&mut self.client).await?.into_row().await
+        })
+        .await?;
+
+        Ok(row
+            .and_then(|row| row.get::<bool, usize>(0))
+            .unwrap_or(false))
+    }
+
+    /// Agrega una etiqueta a la tabla intermedia, devuelve el id asignado.
+    pub async fn insert_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u8> {
+        etiqueta.validar()?;
+
+        let mut insert = Query::new(
+            "INSERT INTO [cambiarEtiquetas].[FaenaEtiquetas]
+                ([enable], [etiqueta], [label], [color], [dpi], [hotkey], [linea])
+            OUTPUT INSERTED.[id]
+            VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7)",
         );
+        insert.bind(etiqueta.enable);
+        insert.bind(etiqueta.etiqueta.clone());
+        insert.bind(etiqueta.label.trim().to_string());
+        insert.bind(etiqueta.color.to_uppercase());
+        insert.bind(etiqueta.dpi.dpi() as i16);
+        insert.bind(etiqueta.hotkey.as_deref());
+        insert.bind(etiqueta.linea.as_str());
+
+        let row = timeout(self.query_timeout, async {
+            insert.query(&mut self.client).await?.into_row().await
+        })
+        .await?;
+
+        row.and_then(|row| row.get::<u8, usize>(0))
+            .context("No se obtuvo el id de la etiqueta agregada.")
+    }
+
+    /// Modifica una etiqueta de la tabla intermedia, la fila se identifica por su id.
+    pub async fn update_etiqueta(&mut self, etiqueta: &Etiquetas) -> anyhow::Result<u64> {
+        etiqueta.validar()?;
 
-        let stream = select.query(&mut self.client).await?;
-        Ok(stream
-            .into_row()
+        let mut update = Query::new(
+            "UPDATE [cambiarEtiquetas].[FaenaEtiquetas]
+            SET [enable] = @P1, [etiqueta] = @P2, [label] = @P3, [color] = @P4, [dpi] = @P5,
+                [hotkey] = @P6, [linea] = @P7
+            WHERE [id] = @P8",
+        );
+        update.bind(etiqueta.enable);
+        update.bind(etiqueta.etiqueta.clone());
+        update.bind(etiqueta.label.trim().to_string());
+        update.bind(etiqueta.color.to_uppercase());
+        update.bind(etiqueta.dpi.dpi() as i16);
+        update.bind(etiqueta.hotkey.as_deref());
+        update.bind(etiqueta.linea.as_str());
+        update.bind(etiqueta.id);
+
+        let rows = timeout(self.query_timeout, update.execute(&mut self.client))
             .await?
+            .total();
+        anyhow::ensure!(rows > 0, "La etiqueta {} no existe.", etiqueta.id);
+
+        Ok(rows)
+    }
+
+    /// Borra una etiqueta de la tabla intermedia.
+    pub async fn delete_etiqueta(&mut self, id: u8) -> anyhow::Result<u64> {
+        let rows = timeout(
+            self.query_timeout,
+            self.client.execute(
+                "DELETE FROM [cambiarEtiquetas].[FaenaEtiquetas] WHERE [id] = @P1",
+                &[&id],
+            ),
+        )
+        .await?
+        .total();
+        anyhow::ensure!(rows > 0, "La etiqueta {id} no existe.");
+
+        Ok(rows)
+    }
+
+    /// Obtiene la etiqueta aplicada actualmente a cada mercaderia de la lista.
+    pub async fn query_etiquetas(&mut self, ids: &str) -> anyhow::Result<Vec<EtiquetaCaja>> {
+        let mut select = Query::new(
+            "SELECT EtiquetasCaja.Mercaderia_Id, EtiquetasCaja.sEtiqueta
+            FROM [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] as EtiquetasCaja
+            WHERE EtiquetasCaja.Mercaderia_Id IN (SELECT item FROM [cambiarEtiquetas].[SplitToList](@P1))",
+        );
+        select.bind(ids);
+
+        let rows = timeout(self.query_timeout, async {
+            select
+                .query(&mut self.client)
+                .await?
+                .into_first_result()
+                .await
+        })
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(EtiquetaCaja {
+                    mercaderia_id: row
+                        .try_get("Mercaderia_Id")?
+                        .context("Columna 'Mercaderia_Id' nula.")?,
+                    etiqueta: row.try_get::<&str, &str>("sEtiqueta")?.map(str::to_string),
+                })
+            })
+            .collect()
+    }
+
+    /// Vuelve cada mercaderia a la etiqueta indicada, todo en una misma transaccion. Las que
+    /// tenian `sEtiqueta` nulo vuelven a nulo.
+    pub async fn restore_etiquetas(
+        &mut self,
+        anteriores: &[EtiquetaCaja],
+    ) -> tiberius::Result<ExecuteResult> {
+        // Agrupa las mercaderias por etiqueta para hacer un UPDATE por cada una.
+        let mut grupos: BTreeMap<Option<&str>, Vec<String>> = BTreeMap::new();
+        for eti in anteriores {
+            grupos
+                .entry(eti.etiqueta.as_deref())
+                .or_default()
+                .push(eti.mercaderia_id.to_string());
+        }
+
+        let mut sql = String::from("SET XACT_ABORT ON;\nBEGIN TRANSACTION;\n");
+        for i in 0..grupos.len() {
+            sql += &format!(
+                "UPDATE [TwinsDBQuatro053].[configuracion].[MercaderiasEtiquetaCaja] SET sEtiqueta = @P{}
+                WHERE Mercaderia_Id IN (SELECT item FROM [cambiarEtiquetas].[SplitToList](@P{}));\n",
+                2 * i + 1,
+                2 * i + 2
+            );
+        }
+        sql += "COMMIT TRANSACTION;";
+
+        let mut update = Query::new(sql);
+        for (etiqueta, ids) in grupos {
+            update.bind(etiqueta.map(str::to_string));
+            update.bind(ids.join(","));
+        }
+
+        timeout(self.query_timeout, update.execute(&mut self.client)).await
+    }
+
+    /// Registra un cambio de etiqueta en la tabla de auditoria.
+    pub async fn insert_registro(&mut self, registro: &RegistroCambio) -> tiberius::Result<()> {
+        timeout(
+            self.query_timeout,
+            self.client.execute(
+                "INSERT INTO [cambiarEtiquetas].[CambiosEtiqueta]
+                    ([estacion], [usuario], [anterior], [nueva], [cantidad], [codigo])
+                VALUES (@P1, @P2, @P3, @P4, @P5, @P6)",
+                &[
+                    &registro.estacion,
+                    &registro.usuario,
+                    &registro.anterior,
+                    &registro.nueva,
+                    &registro.cantidad,
+                    &registro.codigo,
+                ],
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Obtiene los ultimos cambios de etiqueta registrados.
+    pub async fn query_historial(&mut self, limit: usize) -> anyhow::Result<Vec<RegistroCambio>> {
+        let mut select = Query::new(
+            "SELECT TOP (@P1) [fecha], [estacion], [usuario], [anterior], [nueva], [cantidad], [codigo]
+            FROM [cambiarEtiquetas].[CambiosEtiqueta]
+            ORDER BY [fecha] DESC, [id] DESC",
+        );
+        select.bind(limit as i64);
+
+        let rows = timeout(self.query_timeout, async {
+            select
+                .query(&mut self.client)
+                .await?
+                .into_first_result()
+                .await
+        })
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(RegistroCambio {
+                    fecha: row.try_get("fecha")?.context("Columna 'fecha' nula.")?,
+                    estacion: row
+                        .try_get::<&str, &str>("estacion")?
+                        .unwrap_or_default()
+                        .to_string(),
+                    usuario: row
+                        .try_get::<&str, &str>("usuario")?
+                        .unwrap_or_default()
+                        .to_string(),
+                    anterior: row
+                        .try_get::<&str, &str>("anterior")?
+                        .unwrap_or_default()
+                        .to_string(),
+                    nueva: row
+                        .try_get::<&str, &str>("nueva")?
+                        .unwrap_or_default()
+                        .to_string(),
+                    cantidad: row.try_get("cantidad")?.unwrap_or_default(),
+                    codigo: row.try_get("codigo")?.unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Obtiene la lista de mercaderias de la linea, segun su tipo en la tabla `Lineas`.
+    pub async fn query_ids(&mut self, linea: Linea) -> anyhow::Result<String> {
+        // Sin su fila en Lineas la SP no lista nada y pareceria que la linea no tiene medias.
+        let mut select = Query::new(
+            "SELECT COUNT(*) FROM [cambiarEtiquetas].[Lineas] WHERE [linea] = @P1
+            DECLARE @mercaderias varchar(max)
+            EXECUTE [cambiarEtiquetas].[ListarMedias] @mercaderias OUTPUT, @P1",
+        );
+        select.bind(linea.as_str());
+
+        let results = timeout(self.query_timeout, async {
+            select.query(&mut self.client).await?.into_results().await
+        })
+        .await?;
+
+        let registrada = results
+            .first()
+            .and_then(|rows| rows.first())
+            .and_then(|row| row.get::<i32, usize>(0))
+            .unwrap_or_default();
+        anyhow::ensure!(
+            registrada > 0,
+            "La linea '{}' no esta en la tabla Lineas, hay que agregarla con su MercaderiaTipo_Id.",
+            linea.as_str()
+        );
+
+        Ok(results
+            .get(1)
+            .and_then(|rows| rows.first())
             .context("La query 'ListarMedias' fallo.")?
             .get::<&str, usize>(0)
             .context("La query 'ListarMedias' esta vacia.")?
@@ -107,20 +925,118 @@ impl SQL {
     }
 }
 
+/// Limita el tiempo de espera de una consulta, al vencerse devuelve un error de I/O `TimedOut`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn timeout<T>(
+    duration: Duration,
+    query: impl Future<Output = tiberius::Result<T>>,
+) -> tiberius::Result<T> {
+    async_std::future::timeout(duration, query)
+        .await
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Se agoto el tiempo de espera de la consulta.",
+            )
+        })?
+}
+
 #[async_std::test]
 async fn test_sql_connection_and_query_table() {
-    let result = SQL::new_connection().await;
+    let result = SQL::new_connection(&config::Connection::default()).await;
     assert!(result.is_ok());
 
-    let result = result.unwrap().query_table(true).await;
+    let result = result
+        .unwrap()
+        .query_table(Linea::Faena, Resolucion::Dpi300)
+        .await;
     assert!(result.is_ok());
 }
 
 #[async_std::test]
 async fn test_sql_connection_and_query_ids() {
-    let result = SQL::new_connection().await;
+    let result = SQL::new_connection(&config::Connection::default()).await;
     assert!(result.is_ok());
 
-    let result = result.unwrap().query_ids().await;
+    let result = result.unwrap().query_ids(Linea::Faena).await;
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_validar_etiqueta() {
+    let etiqueta = Etiquetas {
+        id: 1,
+        enable: true,
+        etiqueta: "faena_hilton_2copias".to_string(),
+        label: "Hilton 2".to_string(),
+        color: "#B7AA5E".to_string(),
+        dpi: Resolucion::Dpi300,
+        linea: Linea::Faena,
+        hotkey: None,
+    };
+    assert!(etiqueta.validar().is_ok());
+
+    let largo = Etiquetas {
+        label: "Hilton 2 copias grande".to_string(),
+        ..etiqueta.clone()
+    };
+    assert!(largo.validar().is_err());
+
+    for color in ["B7AA5E", "#B7AA5", "#B7AA5EFF", "#B7AZ5E"] {
+        let invalido = Etiquetas {
+            color: color.to_string(),
+            ..etiqueta.clone()
+        };
+        assert_eq!(invalido.validar(), Err(MotivoRechazo::Color), "{color}");
+    }
+    // El motivo que ve el operador sale del catalogo con sus parametros.
+    let motivo = largo.validar().unwrap_err();
+    assert_eq!(motivo, MotivoRechazo::LabelLargo);
+    assert!(motivo.mensaje().contains(&constants::LABEL_LEN.to_string()));
+
+    for (hotkey, valida) in [
+        ("F1", true),
+        ("7", true),
+        ("Enter", false),
+        ("tecla", false),
+    ] {
+        let fila = Etiquetas {
+            hotkey: Some(hotkey.to_string()),
+            ..etiqueta.clone()
+        };
+        assert_eq!(fila.validar().is_ok(), valida, "{hotkey}");
+    }
+
+    // La misma tecla solo se repite en otra linea o resolucion, "1" y "Num1" son la misma.
+    let fila = |id, hotkey: &str, linea| Etiquetas {
+        id,
+        hotkey: Some(hotkey.to_string()),
+        linea,
+        ..etiqueta.clone()
+    };
+    let tabla: TablaEtiquetas = [
+        fila(1, "F1", Linea::Faena),
+        fila(2, "F1", Linea::Cuarteo),
+        fila(3, "1", Linea::Faena),
+        fila(4, "Num1", Linea::Faena),
+        fila(5, "F1", Linea::Faena),
+    ]
+    .into_iter()
+    .map(Ok)
+    .collect();
+    assert_eq!(
+        tabla.filas.iter().map(|eti| eti.id).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        tabla
+            .rechazadas
+            .iter()
+            .map(|fila| fila.id)
+            .collect::<Vec<_>>(),
+        vec![Some(4), Some(5)]
+    );
+    assert!(fila(1, "F1", Linea::Faena)
+        .validar_hotkey(&tabla.filas)
+        .is_ok());
+}
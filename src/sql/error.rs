@@ -0,0 +1,215 @@
+//! Errores de un cambio de etiqueta, clasificados para explicarle al operador que paso y que
+//! puede hacer al respecto.
+
+use crate::{
+    backend::{self, ProcedureError},
+    constants, i18n,
+};
+use std::io;
+use tracing::{error, warn};
+
+/// Motivos por los que puede fallar `CambiarEtiquetas`, segun los codigos que documenta la SP
+/// (ver `sql/deploy_AuxiliarFaena.sql`) mas los problemas de conexion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CambioError {
+    /// 57450: la etiqueta no esta habilitada para producción.
+    NoHabilitada,
+    /// 58450: ninguna de las mercaderias existe o esta activa.
+    MercaderiaInexistente,
+    /// 56450: no se pudo actualizar ninguna mercaderia.
+    FalloUpdate,
+    /// 59450: la etiqueta es de otra linea que las mercaderias.
+    OtraLinea,
+    /// `RAISERROR` de la SP por `@mercaderias` vacia.
+    SinMercaderias,
+    /// `RAISERROR` de la SP por `@etiqueta` vacia.
+    EtiquetaVacia,
+    /// 245: la lista de mercaderias tiene un id que no es numerico.
+    Conversion,
+    /// Se perdio la conexion con la base de datos.
+    SinConexion,
+    /// La consulta supero el `query_timeout` de la configuracion.
+    Timeout,
+    /// Cualquier otro error, con su codigo si lo lanzo la SP.
+    Otro(Option<u32>),
+}
+
+impl CambioError {
+    /// Clasifica un error devuelto por el backend.
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        // El timeout tambien es un error de I/O, se revisa antes que la conexion. Tiberius
+        // convierte el `io::Error` de `sql::timeout` en su propia variante `Io`.
+        let timeout = err.chain().any(|cause| {
+            #[cfg(not(target_arch = "wasm32"))]
+            if matches!(
+                cause.downcast_ref::<tiberius::error::Error>(),
+                Some(tiberius::error::Error::Io {
+                    kind: io::ErrorKind::TimedOut,
+                    ..
+                })
+            ) {
+                return true;
+            }
+
+            cause
+                .downcast_ref::<io::Error>()
+                .is_some_and(|err| err.kind() == io::ErrorKind::TimedOut)
+        });
+        if timeout {
+            return Self::Timeout;
+        }
+        if backend::is_connection_error(err) {
+            return Self::SinConexion;
+        }
+
+        let Some(ProcedureError { code, message }) = err.downcast_ref::<ProcedureError>() else {
+            return Self::Otro(None);
+        };
+        match *code {
+            constants::WARN_CODE => Self::NoHabilitada,
+            constants::MISSING_CODE => Self::MercaderiaInexistente,
+            constants::UPDATE_ERROR_CODE => Self::FalloUpdate,
+            constants::LINEA_CODE => Self::OtraLinea,
+            constants::CONVERSION_CODE => Self::Conversion,
+            // Los dos `RAISERROR` comparten codigo, se distinguen por el parametro que nombran.
+            constants::RAISERROR_CODE if message.contains("@mercaderia") => Self::SinMercaderias,
+            constants::RAISERROR_CODE if message.contains("@etiqueta") => Self::EtiquetaVacia,
+            code => Self::Otro(Some(code)),
+        }
+    }
+
+    /// Codigo de error de la SP, `None` para los errores de conexion.
+    pub fn codigo(&self) -> Option<u32> {
+        match self {
+            Self::NoHabilitada => Some(constants::WARN_CODE),
+            Self::MercaderiaInexistente => Some(constants::MISSING_CODE),
+            Self::FalloUpdate => Some(constants::UPDATE_ERROR_CODE),
+            Self::OtraLinea => Some(constants::LINEA_CODE),
+            Self::SinMercaderias | Self::EtiquetaVacia => Some(constants::RAISERROR_CODE),
+            Self::Conversion => Some(constants::CONVERSION_CODE),
+            Self::SinConexion | Self::Timeout => None,
+            Self::Otro(code) => *code,
+        }
+    }
+
+    /// Nombre corto del error para el log.
+    pub fn tipo(&self) -> &'static str {
+        match self {
+            Self::NoHabilitada => "no_habilitada",
+            Self::MercaderiaInexistente => "mercaderia_inexistente",
+            Self::FalloUpdate => "fallo_update",
+            Self::OtraLinea => "otra_linea",
+            Self::SinMercaderias => "sin_mercaderias",
+            Self::EtiquetaVacia => "etiqueta_vacia",
+            Self::Conversion => "conversion",
+            Self::SinConexion => "sin_conexion",
+            Self::Timeout => "timeout",
+            Self::Otro(_) => "otro",
+        }
+    }
+
+    /// Las advertencias no son fallas, la SP rechazo el cambio a proposito.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::NoHabilitada)
+    }
+
+    /// Mensaje para el operador, en el idioma de la interfaz.
+    pub fn mensaje(&self) -> &'static str {
+        match self {
+            Self::NoHabilitada => i18n::t("error.no_habilitada"),
+            Self::MercaderiaInexistente => i18n::t("error.mercaderia_inexistente"),
+            Self::FalloUpdate => i18n::t("error.fallo_update"),
+            Self::OtraLinea => i18n::t("error.otra_linea"),
+            Self::SinMercaderias => i18n::t("error.sin_mercaderias"),
+            Self::EtiquetaVacia => i18n::t("error.etiqueta_vacia"),
+            Self::Conversion => i18n::t("error.conversion"),
+            Self::SinConexion => i18n::t("error.sin_conexion"),
+            Self::Timeout => i18n::t("error.timeout"),
+            Self::Otro(_) => i18n::t("error.otro"),
+        }
+    }
+
+    /// Que puede hacer el operador para resolverlo, en el idioma de la interfaz.
+    pub fn sugerencia(&self) -> &'static str {
+        match self {
+            Self::NoHabilitada => i18n::t("sugerencia.no_habilitada"),
+            Self::MercaderiaInexistente => i18n::t("sugerencia.mercaderia_inexistente"),
+            Self::FalloUpdate => i18n::t("sugerencia.fallo_update"),
+            Self::OtraLinea => i18n::t("sugerencia.otra_linea"),
+            Self::SinMercaderias => i18n::t("sugerencia.sin_mercaderias"),
+            Self::EtiquetaVacia => i18n::t("sugerencia.etiqueta_vacia"),
+            Self::Conversion => i18n::t("sugerencia.conversion"),
+            Self::SinConexion => i18n::t("sugerencia.sin_conexion"),
+            Self::Timeout => i18n::t("sugerencia.timeout"),
+            Self::Otro(_) => i18n::t("sugerencia.otro"),
+        }
+    }
+
+    /// Registra el error en el log con sus campos, para poder filtrarlos.
+    pub fn log(&self, operacion: &str, err: &anyhow::Error) {
+        let codigo = self.codigo().map_or(-1, |code| code as i64);
+        if self.is_warning() {
+            warn!(operacion, tipo = self.tipo(), codigo, motivo = %err, "Cambio de etiqueta rechazado");
+        } else {
+            error!(operacion, tipo = self.tipo(), codigo, motivo = %err, "Fallo el cambio de etiqueta");
+        }
+    }
+}
+
+#[async_std::test]
+async fn test_cambio_error() {
+    use crate::backend::{Backend, Memory, Origen};
+
+    let mut memory = Memory::default();
+    for (ids, etiqueta, esperado) in [
+        ("", "faena_hilton_2copias", CambioError::SinMercaderias),
+        ("11", "", CambioError::EtiquetaVacia),
+        ("11,a", "faena_hilton_2copias", CambioError::Conversion),
+        (
+            "-1",
+            "faena_hilton_2copias",
+            CambioError::MercaderiaInexistente,
+        ),
+    ] {
+        let err = memory
+            .execute_cambiar_etiquetas(ids, etiqueta, &Origen::local())
+            .await
+            .unwrap_err();
+        assert_eq!(CambioError::from_error(&err), esperado, "{ids} {etiqueta}");
+    }
+
+    let timeout = anyhow::Error::from(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+    assert_eq!(CambioError::from_error(&timeout), CambioError::Timeout);
+    // El mismo error que devuelve una consulta SQL vencida.
+    let timeout = super::timeout(
+        std::time::Duration::from_millis(1),
+        std::future::pending::<tiberius::Result<()>>(),
+    )
+    .await
+    .map_err(anyhow::Error::from)
+    .unwrap_err();
+    assert_eq!(CambioError::from_error(&timeout), CambioError::Timeout);
+    let perdida = anyhow::Error::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+    assert_eq!(CambioError::from_error(&perdida), CambioError::SinConexion);
+    assert_eq!(
+        CambioError::from_error(&anyhow::anyhow!("otro")),
+        CambioError::Otro(None)
+    );
+}
+
+#[test]
+fn test_cambio_error_log() {
+    // Los rechazos y las fallas llegan al log con sus campos.
+    let err = anyhow::Error::from(ProcedureError {
+        code: constants::WARN_CODE,
+        message: "Etiqueta faena_h_aa_4copias no habilitada para producción.".to_string(),
+    });
+    let log = crate::capturar_log(|| CambioError::from_error(&err).log("cambiar", &err));
+    assert!(log.contains("Cambio de etiqueta rechazado"), "{log}");
+    assert!(log.contains("tipo=\"no_habilitada\""), "{log}");
+    assert!(log.contains("codigo=57450"), "{log}");
+
+    let err = anyhow::Error::from(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+    let log = crate::capturar_log(|| CambioError::from_error(&err).log("cambiar", &err));
+    assert!(log.contains("Fallo el cambio de etiqueta"), "{log}");
+}
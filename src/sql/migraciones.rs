@@ -0,0 +1,249 @@
+//! Migraciones del esquema de `AuxiliarFaena`, numeradas y embebidas en el programa.
+//!
+//! Cada migracion es un archivo de `sql/migrations` con sus lotes separados por `GO`, como los
+//! ejecuta SSMS. La tabla `SchemaVersion` registra las aplicadas; el script de deploy crea la
+//! base con todas ellas ya registradas.
+
+use super::SQL;
+use tiberius::Query;
+use tracing::{error, info};
+
+/// Migracion del esquema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migracion {
+    /// Numero de la migracion, es el prefijo del archivo
+    pub version: i32,
+    pub nombre: &'static str,
+    pub sql: &'static str,
+}
+
+/// Crea `SchemaVersion` si no existe, no cuenta como migracion.
+const SCHEMA_VERSION: &str = include_str!("../../sql/migrations/000_schema_version.sql");
+
+/// Migraciones conocidas, en orden. Al agregar una, tambien se registra en el script de deploy.
+const MIGRACIONES: &[Migracion] = &[
+    Migracion {
+        version: 1,
+        nombre: "cambios_etiqueta",
+        sql: include_str!("../../sql/migrations/001_cambios_etiqueta.sql"),
+    },
+    Migracion {
+        version: 2,
+        nombre: "etiquetas_admin",
+        sql: include_str!("../../sql/migrations/002_etiquetas_admin.sql"),
+    },
+    Migracion {
+        version: 3,
+        nombre: "cambiar_etiquetas",
+        sql: include_str!("../../sql/migrations/003_cambiar_etiquetas.sql"),
+    },
+    Migracion {
+        version: 4,
+        nombre: "hotkey",
+        sql: include_str!("../../sql/migrations/004_hotkey.sql"),
+    },
+    Migracion {
+        version: 5,
+        nombre: "resolucion",
+        sql: include_str!("../../sql/migrations/005_resolucion.sql"),
+    },
+    Migracion {
+        version: 6,
+        nombre: "linea",
+        sql: include_str!("../../sql/migrations/006_linea.sql"),
+    },
+    Migracion {
+        version: 7,
+        nombre: "lineas",
+        sql: include_str!("../../sql/migrations/007_lineas.sql"),
+    },
+];
+
+impl Migracion {
+    /// Lotes del script, separados por las lineas `GO`.
+    pub fn lotes(&self) -> Vec<&'static str> {
+        lotes(self.sql)
+    }
+}
+
+/// Version mas nueva que conoce el programa.
+fn ultima_version() -> i32 {
+    MIGRACIONES.last().map_or(0, |migracion| migracion.version)
+}
+
+/// Migraciones posteriores a `version`. Falla si la base tiene un esquema mas nuevo que el
+/// programa, porque podria no ser compatible con el.
+fn pendientes(version: i32) -> anyhow::Result<&'static [Migracion]> {
+    let ultima = ultima_version();
+    anyhow::ensure!(
+        version <= ultima,
+        "La base tiene el esquema {version}, mas nuevo que el {ultima} que conoce el programa. \
+        Actualice el programa."
+    );
+
+    Ok(&MIGRACIONES[MIGRACIONES.partition_point(|migracion| migracion.version <= version)..])
+}
+
+/// Separa un script en lotes por las lineas que solo tienen `GO`.
+fn lotes(sql: &str) -> Vec<&str> {
+    let mut lotes = Vec::new();
+    let mut inicio = 0;
+    let mut pos = 0;
+    for linea in sql.split_inclusive('\n') {
+        if linea.trim().eq_ignore_ascii_case("go") {
+            lotes.push(&sql[inicio..pos]);
+            inicio = pos + linea.len();
+        }
+        pos += linea.len();
+    }
+    lotes.push(&sql[inicio..]);
+
+    lotes
+        .into_iter()
+        .map(str::trim)
+        .filter(|lote| !lote.is_empty())
+        .collect()
+}
+
+impl SQL {
+    /// Version del esquema aplicada, 0 si la base todavia no tiene `SchemaVersion`.
+    pub async fn schema_version(&mut self) -> anyhow::Result<i32> {
+        let row = self
+            .client
+            .simple_query(
+                "IF OBJECT_ID('[cambiarEtiquetas].[SchemaVersion]', 'U') IS NULL
+                    SELECT 0
+                ELSE
+                    SELECT ISNULL(MAX([version]), 0) FROM [cambiarEtiquetas].[SchemaVersion]",
+            )
+            .await?
+            .into_row()
+            .await?;
+
+        Ok(row.and_then(|row| row.get::<i32, usize>(0)).unwrap_or(0))
+    }
+
+    /// Migraciones que faltan aplicar en la base.
+    pub async fn migraciones_pendientes(&mut self) -> anyhow::Result<&'static [Migracion]> {
+        pendientes(self.schema_version().await?)
+    }
+
+    /// Aplica las migraciones pendientes, cada una en su propia transaccion junto con su
+    /// registro en `SchemaVersion`. Devuelve las aplicadas.
+    ///
+    /// Sin el limite de `query_timeout`: una migracion puede tardar mas que una consulta.
+    pub async fn migrar(&mut self) -> anyhow::Result<Vec<Migracion>> {
+        for lote in lotes(SCHEMA_VERSION) {
+            self.client.simple_query(lote).await?.into_results().await?;
+        }
+
+        let pendientes = pendientes(self.schema_version().await?)?;
+        for migracion in pendientes {
+            if let Err(err) = self.aplicar(migracion).await {
+                error!(
+                    version = migracion.version,
+                    "On sql::migrar {}: {err}", migracion.nombre
+                );
+                // Si la conexion sigue viva se descarta lo que haya quedado a medias.
+                if let Ok(rollback) = self
+                    .client
+                    .simple_query("IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION")
+                    .await
+                {
+                    let _ = rollback.into_results().await;
+                }
+                return Err(err.context(format!(
+                    "Fallo la migracion {:03}_{}, no se aplico.",
+                    migracion.version, migracion.nombre
+                )));
+            }
+            info!(
+                version = migracion.version,
+                "Migracion {} aplicada", migracion.nombre
+            );
+        }
+
+        Ok(pendientes.to_vec())
+    }
+
+    /// Registra como aplicadas las migraciones hasta `version`, sin ejecutarlas. Sirve para las
+    /// bases creadas antes de `SchemaVersion` a las que ya se les aplicaron a mano.
+    pub async fn marcar_migraciones(&mut self, version: i32) -> anyhow::Result<Vec<Migracion>> {
+        for lote in lotes(SCHEMA_VERSION) {
+            self.client.simple_query(lote).await?.into_results().await?;
+        }
+
+        let actual = self.schema_version().await?;
+        pendientes(version)?;
+        let marcadas: Vec<Migracion> = pendientes(actual)?
+            .iter()
+            .take_while(|migracion| migracion.version <= version)
+            .copied()
+            .collect();
+
+        for migracion in &marcadas {
+            self.registrar(migracion).await?;
+        }
+
+        Ok(marcadas)
+    }
+
+    /// Ejecuta los lotes de la migracion y su registro en una transaccion.
+    async fn aplicar(&mut self, migracion: &Migracion) -> anyhow::Result<()> {
+        // Con XACT_ABORT cualquier error deshace la transaccion completa.
+        self.client
+            .simple_query("SET XACT_ABORT ON; BEGIN TRANSACTION")
+            .await?
+            .into_results()
+            .await?;
+
+        for lote in migracion.lotes() {
+            self.client.simple_query(lote).await?.into_results().await?;
+        }
+        self.registrar(migracion).await?;
+
+        self.client
+            .simple_query("COMMIT TRANSACTION")
+            .await?
+            .into_results()
+            .await?;
+        Ok(())
+    }
+
+    /// Inserta la migracion en `SchemaVersion`.
+    async fn registrar(&mut self, migracion: &Migracion) -> anyhow::Result<()> {
+        let mut insert = Query::new(
+            "INSERT INTO [cambiarEtiquetas].[SchemaVersion] ([version], [nombre]) VALUES (@P1, @P2)",
+        );
+        insert.bind(migracion.version);
+        insert.bind(migracion.nombre);
+        insert.execute(&mut self.client).await?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_migraciones() {
+    // Numeradas desde 1 sin saltos, con al menos un lote y registradas en el deploy.
+    let deploy = include_str!("../../sql/deploy_AuxiliarFaena.sql");
+    for (i, migracion) in MIGRACIONES.iter().enumerate() {
+        assert_eq!(migracion.version, i as i32 + 1);
+        assert!(!migracion.lotes().is_empty(), "{}", migracion.nombre);
+        assert!(
+            deploy.contains(&format!("({}, '{}')", migracion.version, migracion.nombre)),
+            "{}",
+            migracion.nombre
+        );
+    }
+
+    assert_eq!(pendientes(0).unwrap(), MIGRACIONES);
+    assert_eq!(pendientes(1).unwrap(), &MIGRACIONES[1..]);
+    assert!(pendientes(ultima_version()).unwrap().is_empty());
+    assert!(pendientes(ultima_version() + 1).is_err());
+
+    let script = "CREATE TABLE t (go INT);\nGO\n\n  go  \nSELECT 1\r\nGo\r\nSELECT 2";
+    assert_eq!(
+        lotes(script),
+        vec!["CREATE TABLE t (go INT);", "SELECT 1", "SELECT 2"]
+    );
+}
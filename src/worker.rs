@@ -0,0 +1,299 @@
+//! Tarea asincrona que es dueña de la conexion con la base de datos.
+//!
+//! La interfaz le envia pedidos por un canal y recibe las respuestas por otro, de esta forma
+//! nunca se bloquea esperando al servidor y las operaciones se ejecutan de a una.
+
+use crate::{
+    backend::{self, Backend, CambioAplicado, ConnectionState, Origen},
+    config::{Config, Estacion},
+    sql::{Cambio, EtiquetaCaja, Etiquetas, FilaRechazada, RegistroCambio, Resolucion},
+};
+use async_std::channel::{self, Receiver, Sender};
+use tracing::error;
+
+/// Pedidos que la interfaz le hace al worker.
+#[derive(Debug)]
+pub enum Command {
+    /// Recarga la tabla intermedia, las medias y sus etiquetas. Si `history_len` no es `None`
+    /// tambien recarga el historial.
+    Refresh {
+        resolucion: Resolucion,
+        history_len: Option<usize>,
+    },
+    /// Ultimos cambios de etiqueta.
+    Historial(usize),
+    /// Ejecuta `CambiarEtiquetas` en modo producción.
+    CambiarEtiquetas { ids: String, etiqueta: String },
+    /// Ejecuta `CambiarEtiquetas` en modo prueba.
+    ProbarCambio { ids: String, etiqueta: String },
+    /// Deshace un cambio volviendo a las etiquetas anteriores.
+    Restore(Vec<EtiquetaCaja>),
+    /// Pedidos de la pantalla de administracion.
+    Admin(Admin),
+}
+
+/// Modificaciones a la tabla intermedia, todas verifican antes los permisos del usuario.
+#[derive(Debug)]
+pub enum Admin {
+    /// Solo obtiene la tabla completa.
+    Abrir,
+    Agregar(Etiquetas),
+    Editar(Etiquetas),
+    Borrar(u8),
+}
+
+/// Respuestas del worker, una por cada [`Command`] mas los cambios de estado de la conexion.
+#[derive(Debug)]
+pub enum Response {
+    Connection(ConnectionState),
+    Refresh(anyhow::Result<Snapshot>),
+    Historial(anyhow::Result<Vec<RegistroCambio>>),
+    Cambio(anyhow::Result<CambioAplicado>),
+    Preview(anyhow::Result<Vec<Cambio>>),
+    Restored(anyhow::Result<u64>),
+    /// Tabla intermedia completa luego de aplicar el pedido de administracion.
+    Admin(anyhow::Result<Vec<Etiquetas>>),
+}
+
+/// Estado de la base de datos que muestra la interfaz.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    /// Tabla intermedia, filtrada por linea y dpi
+    pub table: Vec<Etiquetas>,
+    /// Filas de la tabla intermedia descartadas por invalidas
+    pub rechazadas: Vec<FilaRechazada>,
+    /// Lista de medias separada por comas
+    pub faena_ids: String,
+    /// Etiqueta aplicada a cada media
+    pub etiquetas: Vec<EtiquetaCaja>,
+    /// Ultimos cambios, solo si se pidio
+    pub historial: Option<Vec<RegistroCambio>>,
+}
+
+impl Command {
+    /// Respuesta de error para un pedido que no se llego a ejecutar.
+    fn fail(self, err: anyhow::Error) -> Response {
+        match self {
+            Command::Refresh { .. } => Response::Refresh(Err(err)),
+            Command::Historial(_) => Response::Historial(Err(err)),
+            Command::CambiarEtiquetas { .. } => Response::Cambio(Err(err)),
+            Command::ProbarCambio { .. } => Response::Preview(Err(err)),
+            Command::Restore(_) => Response::Restored(Err(err)),
+            Command::Admin(_) => Response::Admin(Err(err)),
+        }
+    }
+}
+
+impl Response {
+    /// Indica si el pedido fallo por perdida de la conexion.
+    fn is_connection_error(&self) -> bool {
+        let err = match self {
+            Response::Connection(_) => None,
+            Response::Refresh(result) => result.as_ref().err(),
+            Response::Historial(result) => result.as_ref().err(),
+            Response::Cambio(result) => result.as_ref().err(),
+            Response::Preview(result) => result.as_ref().err(),
+            Response::Restored(result) => result.as_ref().err(),
+            Response::Admin(result) => result.as_ref().err(),
+        };
+
+        err.is_some_and(backend::is_connection_error)
+    }
+}
+
+/// Extremo de la interfaz para comunicarse con el worker.
+#[derive(Debug)]
+pub struct Worker {
+    commands: Sender<Command>,
+    responses: Receiver<Response>,
+}
+
+impl Worker {
+    /// Lanza el worker, `notify` se llama con cada respuesta para despertar a la interfaz.
+    pub fn spawn(config: Config, notify: impl Fn() + Send + Sync + 'static) -> Self {
+        let (commands, rx) = channel::unbounded();
+        let (tx, responses) = channel::unbounded();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        async_std::task::spawn(run(config, rx, tx, notify));
+        // En el navegador no hay hilos, la tarea corre en el loop de eventos de la pagina.
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(run(config, rx, tx, notify));
+
+        Self {
+            commands,
+            responses,
+        }
+    }
+
+    /// Encola un pedido, la respuesta llega por [`Worker::try_recv`].
+    pub fn send(&self, command: Command) {
+        if let Err(err) = self.commands.try_send(command) {
+            error!("El worker no esta disponible: {err}");
+        }
+    }
+
+    /// Siguiente respuesta disponible, no bloquea.
+    pub fn try_recv(&self) -> Option<Response> {
+        self.responses.try_recv().ok()
+    }
+}
+
+/// Loop del worker, termina cuando la interfaz suelta su extremo del canal.
+async fn run(
+    config: Config,
+    commands: Receiver<Command>,
+    responses: Sender<Response>,
+    notify: impl Fn() + Send + Sync + 'static,
+) {
+    let send = |response: Response| {
+        // Si la interfaz ya no esta, el loop termina en el proximo `recv`.
+        let _ = responses.try_send(response);
+        notify();
+    };
+
+    let mut sql: Option<Box<dyn Backend>> = None;
+    loop {
+        let backend = match sql.as_mut() {
+            Some(backend) => backend,
+            None => {
+                let backend =
+                    backend::reconnect(&config, |state| send(Response::Connection(state))).await;
+
+                // Los pedidos encolados mientras no habia conexion quedaron viejos, no se
+                // ejecutan para no aplicar un cambio de etiqueta minutos despues.
+                while let Ok(command) = commands.try_recv() {
+                    send(command.fail(anyhow::anyhow!("Pedido descartado por falta de conexion.")));
+                }
+                // Recien ahora, asi la recarga que dispara en la interfaz no se descarta.
+                send(Response::Connection(ConnectionState::Connected));
+
+                sql.insert(backend)
+            }
+        };
+
+        let Ok(command) = commands.recv().await else {
+            return;
+        };
+
+        let response = execute(backend.as_mut(), &config, command).await;
+        if response.is_connection_error() {
+            sql = None;
+        }
+        send(response);
+    }
+}
+
+async fn execute(sql: &mut dyn Backend, config: &Config, command: Command) -> Response {
+    match command {
+        Command::Refresh {
+            resolucion,
+            history_len,
+        } => Response::Refresh(refresh(sql, &config.estacion, resolucion, history_len).await),
+        Command::Historial(limit) => Response::Historial(sql.query_historial(limit).await),
+        Command::CambiarEtiquetas { ids, etiqueta } => Response::Cambio(
+            sql.execute_cambiar_etiquetas(&ids, &etiqueta, &Origen::local())
+                .await,
+        ),
+        Command::ProbarCambio { ids, etiqueta } => {
+            Response::Preview(sql.query_cambiar_etiquetas(&ids, &etiqueta).await)
+        }
+        Command::Restore(anteriores) => {
+            Response::Restored(sql.restore_etiquetas(&anteriores, &Origen::local()).await)
+        }
+        Command::Admin(admin) => Response::Admin(administrar(sql, &config.admin_role, admin).await),
+    }
+}
+
+async fn administrar(
+    sql: &mut dyn Backend,
+    role: &str,
+    admin: Admin,
+) -> anyhow::Result<Vec<Etiquetas>> {
+    anyhow::ensure!(
+        sql.is_admin(role).await?,
+        "El usuario no pertenece al rol {role}, no puede administrar las etiquetas."
+    );
+
+    match admin {
+        Admin::Abrir => {}
+        Admin::Agregar(etiqueta) => {
+            sql.insert_etiqueta(&etiqueta).await?;
+        }
+        Admin::Editar(etiqueta) => {
+            sql.update_etiqueta(&etiqueta).await?;
+        }
+        Admin::Borrar(id) => {
+            sql.delete_etiqueta(id).await?;
+        }
+    }
+
+    sql.query_table_completa().await
+}
+
+async fn refresh(
+    sql: &mut dyn Backend,
+    estacion: &Estacion,
+    resolucion: Resolucion,
+    history_len: Option<usize>,
+) -> anyhow::Result<Snapshot> {
+    let table = sql.query_table(estacion.linea, resolucion).await?;
+    let faena_ids = sql.query_ids(estacion.linea).await?;
+
+    // Sin las etiquetas aplicadas o el historial la interfaz igual puede operar.
+    let etiquetas = sql.query_etiquetas(&faena_ids).await.unwrap_or_else(|err| {
+        error!("On sql::query_etiquetas: {err}");
+        Vec::new()
+    });
+    let historial = match history_len {
+        Some(limit) => Some(sql.query_historial(limit).await.unwrap_or_else(|err| {
+            error!("On sql::query_historial: {err}");
+            Vec::new()
+        })),
+        None => None,
+    };
+
+    Ok(Snapshot {
+        table: table.filas,
+        rechazadas: table.rechazadas,
+        faena_ids,
+        etiquetas,
+        historial,
+    })
+}
+
+#[async_std::test]
+async fn test_worker_memory() {
+    let config = Config {
+        backend: crate::config::BackendKind::Memory,
+        ..Default::default()
+    };
+    let worker = Worker::spawn(config, || {});
+
+    // Los pedidos previos a la conexion se descartan, hay que esperar a que avise. Para
+    // entonces ya vacio la cola, y lo que se pida en respuesta se ejecuta.
+    while !matches!(
+        worker.responses.recv().await.unwrap(),
+        Response::Connection(ConnectionState::Connected)
+    ) {}
+
+    worker.send(Command::CambiarEtiquetas {
+        ids: "11,12".to_string(),
+        etiqueta: "faena_hilton_2copias".to_string(),
+    });
+    worker.send(Command::Refresh {
+        resolucion: Resolucion::Dpi300,
+        history_len: Some(10),
+    });
+
+    let cambio = worker.responses.recv().await.unwrap();
+    assert!(matches!(
+        cambio,
+        Response::Cambio(Ok(CambioAplicado { rows: 2, .. }))
+    ));
+
+    let Response::Refresh(Ok(snapshot)) = worker.responses.recv().await.unwrap() else {
+        panic!("Se esperaba la respuesta de Refresh");
+    };
+    assert_eq!(snapshot.historial.map(|historial| historial.len()), Some(1));
+}